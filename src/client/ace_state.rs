@@ -1,5 +1,16 @@
-use std::{time::Instant, sync::Arc};
 use futures_util::lock::Mutex;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// A hotbar key press waiting for the telemetry to show the slot held
+pub struct SlotSwitch {
+    pub slot: char,
+    pub pressed_at: Instant,
+    pub attempts: u32,
+}
+
 pub struct AceState {
     pub last_flight: Instant,
     pub is_turning: bool,
@@ -9,7 +20,21 @@ pub struct AceState {
     pub last_shift_press: Instant,
 
     pub is_pressing_right_click: bool,
+    /// Left button held down by a mining step
+    pub is_pressing_left_click: bool,
+
+    /// Hotbar slot last selected, trusted when the telemetry does not tell
+    pub hand_slot: Option<char>,
+    /// Hotbar key pressed and not yet seen in the telemetry
+    pub slot_switch: Option<SlotSwitch>,
+    /// Slot given up on and the one the telemetry showed held, for the run to pause
+    pub failed_slot_switch: Option<(char, Option<char>)>,
     pub hand_stack_reseted: bool,
+
+    /// Keys held by pulse tasks and the moment they are released
+    pub pulses: Vec<(&'static str, Instant)>,
+    /// Flight key pulses sent since start, for the run report
+    pub flight_pulses: u64,
 }
 
 impl AceState {
@@ -23,7 +48,43 @@ impl AceState {
             is_moving: false,
 
             is_pressing_right_click: false,
+            is_pressing_left_click: false,
+
+            hand_slot: None,
+            slot_switch: None,
+            failed_slot_switch: None,
             hand_stack_reseted: false,
+
+            pulses: Vec::new(),
+            flight_pulses: 0,
         }))
     }
+
+    pub fn pulse(&mut self, key: &'static str, duration: Duration) {
+        self.pulses.push((key, Instant::now() + duration));
+    }
+
+    /// Inputs the bot is currently holding, for the status frame
+    pub fn active_inputs(&mut self) -> Vec<&'static str> {
+        let now = Instant::now();
+        self.pulses.retain(|(_, release_at)| *release_at > now);
+
+        let mut inputs: Vec<&'static str> = self.pulses.iter().map(|(key, _)| *key).collect();
+
+        if self.is_turning {
+            inputs.push("look");
+        }
+
+        if self.is_pressing_right_click {
+            inputs.push("right_click");
+        }
+
+        if self.is_pressing_left_click {
+            inputs.push("left_click");
+        }
+
+        inputs.sort_unstable();
+        inputs.dedup();
+        inputs
+    }
 }
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::DISTANCE_TO_FINISH_INSTRUCTION;
+
+/// Commands observers can send through the relay as
+/// `{ "type": "command", "id": "...", "command": "<name>", ...fields }`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    /// Replaces the running plan with the plan file at `plan`, or the built-in one
+    Start { plan: Option<String> },
+    Pause,
+    Resume,
+    Abort,
+    Skip,
+    Set { name: String, value: f64 },
+}
+
+impl Command {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Start { .. } => "start",
+            Command::Pause => "pause",
+            Command::Resume => "resume",
+            Command::Abort => "abort",
+            Command::Skip => "skip",
+            Command::Set { .. } => "set",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CommandFrame {
+    pub id: String,
+    #[serde(flatten)]
+    pub command: Command,
+}
+
+#[derive(Debug)]
+pub enum Incoming {
+    Command(CommandFrame),
+    /// A command frame that could not be parsed, answered with a failed ack
+    Invalid { id: String, error: String },
+    /// Typed frames meant for someone else, such as other bots' acks
+    Ignored,
+}
+
+pub fn parse(value: Value) -> Incoming {
+    if value["type"] != "command" {
+        return Incoming::Ignored;
+    }
+
+    let id = value["id"].as_str().unwrap_or_default().to_owned();
+
+    match serde_json::from_value::<CommandFrame>(value) {
+        Ok(frame) => Incoming::Command(frame),
+        Err(error) => Incoming::Invalid {
+            id,
+            error: error.to_string(),
+        },
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename = "ack")]
+pub struct Ack {
+    pub id: String,
+    pub command: Option<&'static str>,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+impl Ack {
+    pub fn new(frame: &CommandFrame, result: Result<(), String>) -> Self {
+        Self {
+            id: frame.id.clone(),
+            command: Some(frame.command.name()),
+            ok: result.is_ok(),
+            error: result.err(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// Tunables the `set` command can change while a plan runs
+#[derive(Debug, Clone, Copy)]
+pub struct Parameters {
+    pub distance_to_finish: f64,
+    pub vertical_angle: f64,
+}
+
+impl Parameters {
+    pub fn new() -> Self {
+        Self {
+            distance_to_finish: DISTANCE_TO_FINISH_INSTRUCTION,
+            vertical_angle: 5.0,
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: f64) -> Result<(), String> {
+        match name {
+            "distance_to_finish" if value > 0.0 => self.distance_to_finish = value,
+            "distance_to_finish" => return Err("distance_to_finish must be positive".to_owned()),
+            "vertical_angle" => self.vertical_angle = value.clamp(-90.0, 90.0),
+            _ => return Err(format!("unknown parameter '{name}'")),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use std::{collections::BTreeMap, fs, io::ErrorKind};
+
+use serde::Deserialize;
+
+use crate::{url_query, WEBSOCKET_URL};
+
+const CONFIG_PATH: &str = "config.toml";
+
+/// Connection settings from the `[client]` table of `config.toml`
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ClientConfig {
+    pub websocket_url: String,
+    pub token: Option<String>,
+    pub room: String,
+    pub role: String,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            websocket_url: WEBSOCKET_URL.to_owned(),
+            token: None,
+            room: "default".to_owned(),
+            role: "bot".to_owned(),
+        }
+    }
+}
+
+/// Movement speeds in blocks per second and the blocks at hand, from the
+/// `[planner]` table, used to estimate a plan before it runs
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PlannerConfig {
+    pub walk_speed: f64,
+    pub sprint_speed: f64,
+    pub fly_speed: f64,
+    pub vertical_speed: f64,
+    /// Walk legs longer than this are sprinted
+    pub sprint_distance: f64,
+    /// Blocks placed per second on right-click legs, or one per traversed block when unset
+    pub placement_rate: Option<f64>,
+    /// Blocks carried for each hand slot
+    pub inventory: BTreeMap<char, u64>,
+}
+
+impl Default for PlannerConfig {
+    fn default() -> Self {
+        Self {
+            walk_speed: 4.317,
+            sprint_speed: 5.612,
+            fly_speed: 10.92,
+            vertical_speed: 7.5,
+            sprint_distance: 6.0,
+            placement_rate: None,
+            inventory: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    client: ClientConfig,
+    #[serde(default)]
+    planner: PlannerConfig,
+}
+
+impl ConfigFile {
+    fn load() -> Result<Self, String> {
+        match fs::read_to_string(CONFIG_PATH) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|error| format!("could not parse {CONFIG_PATH}: {error}")),
+
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(Self::default()),
+
+            Err(error) => Err(format!("could not read {CONFIG_PATH}: {error}")),
+        }
+    }
+}
+
+impl PlannerConfig {
+    pub fn load() -> Result<Self, String> {
+        Ok(ConfigFile::load()?.planner)
+    }
+}
+
+impl ClientConfig {
+    pub fn load() -> Self {
+        ConfigFile::load()
+            .unwrap_or_else(|error| panic!("{error}"))
+            .client
+    }
+
+    pub fn connect_url(&self) -> String {
+        format!(
+            "{}?room={}&role={}",
+            self.websocket_url,
+            url_query::encode(&self.room),
+            url_query::encode(&self.role)
+        )
+    }
+
+    /// Handshake for the relay, authenticated when a token is configured
+    pub fn websocket_request(&self) -> awc::ws::WebsocketsRequest {
+        let request = awc::Client::new().ws(self.connect_url());
+
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+}
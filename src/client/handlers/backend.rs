@@ -0,0 +1,106 @@
+use enigo::{Enigo, Key, KeyboardControllable, MouseButton, MouseControllable};
+
+/// Keyboard and mouse the handlers press, Enigo outside of tests
+pub trait InputBackend {
+    fn key_down(&mut self, key: Key);
+    fn key_up(&mut self, key: Key);
+    fn key_click(&mut self, key: Key);
+    fn key_sequence(&mut self, text: &str);
+    fn mouse_down(&mut self, button: MouseButton);
+    fn mouse_up(&mut self, button: MouseButton);
+    fn mouse_click(&mut self, button: MouseButton);
+    fn mouse_move_relative(&mut self, x: i32, y: i32);
+}
+
+impl InputBackend for Enigo {
+    fn key_down(&mut self, key: Key) {
+        KeyboardControllable::key_down(self, key);
+    }
+
+    fn key_up(&mut self, key: Key) {
+        KeyboardControllable::key_up(self, key);
+    }
+
+    fn key_click(&mut self, key: Key) {
+        KeyboardControllable::key_click(self, key);
+    }
+
+    fn key_sequence(&mut self, text: &str) {
+        KeyboardControllable::key_sequence(self, text);
+    }
+
+    fn mouse_down(&mut self, button: MouseButton) {
+        MouseControllable::mouse_down(self, button);
+    }
+
+    fn mouse_up(&mut self, button: MouseButton) {
+        MouseControllable::mouse_up(self, button);
+    }
+
+    fn mouse_click(&mut self, button: MouseButton) {
+        MouseControllable::mouse_click(self, button);
+    }
+
+    fn mouse_move_relative(&mut self, x: i32, y: i32) {
+        MouseControllable::mouse_move_relative(self, x, y);
+    }
+}
+
+/// Records what the handlers press instead of pressing it
+#[cfg(test)]
+pub mod mock {
+    use enigo::{Key, MouseButton};
+
+    use super::InputBackend;
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Press {
+        KeyDown(Key),
+        KeyUp(Key),
+        KeyClick(Key),
+        Text(String),
+        MouseDown(MouseButton),
+        MouseUp(MouseButton),
+        MouseClick(MouseButton),
+        MouseMove(i32, i32),
+    }
+
+    #[derive(Debug, Default)]
+    pub struct Recorder {
+        pub presses: Vec<Press>,
+    }
+
+    impl InputBackend for Recorder {
+        fn key_down(&mut self, key: Key) {
+            self.presses.push(Press::KeyDown(key));
+        }
+
+        fn key_up(&mut self, key: Key) {
+            self.presses.push(Press::KeyUp(key));
+        }
+
+        fn key_click(&mut self, key: Key) {
+            self.presses.push(Press::KeyClick(key));
+        }
+
+        fn key_sequence(&mut self, text: &str) {
+            self.presses.push(Press::Text(text.to_owned()));
+        }
+
+        fn mouse_down(&mut self, button: MouseButton) {
+            self.presses.push(Press::MouseDown(button));
+        }
+
+        fn mouse_up(&mut self, button: MouseButton) {
+            self.presses.push(Press::MouseUp(button));
+        }
+
+        fn mouse_click(&mut self, button: MouseButton) {
+            self.presses.push(Press::MouseClick(button));
+        }
+
+        fn mouse_move_relative(&mut self, x: i32, y: i32) {
+            self.presses.push(Press::MouseMove(x, y));
+        }
+    }
+}
@@ -1,21 +1,33 @@
 use enigo::*;
+use tracing::debug;
 
 use crate::{
     ace_state::AceState,
-    instructions::{Instruction, Looking},
+    handlers::input,
+    instructions::{control::Control, Instruction, Looking},
 };
 
 pub fn handle_finished(enigo: &mut Enigo, ace_state: &mut AceState, instruction: &Instruction) {
     ace_state.hand_stack_reseted = false;
 
-    println!("Releasing All (Instruction Finished)");
+    debug!("releasing all keys");
     if ace_state.is_pressing_right_click {
         enigo.mouse_up(MouseButton::Right);
         ace_state.is_pressing_right_click = false;
     };
 
+    if ace_state.is_pressing_left_click {
+        enigo.mouse_up(MouseButton::Left);
+        ace_state.is_pressing_left_click = false;
+    };
+
     enigo.key_up(Key::Control);
 
+    // a hold step cut short by a pause or a skip lets go right away
+    if let Some(Control::Hold { input, .. }) = &instruction.control {
+        input::release(enigo, input);
+    }
+
     match instruction.looking {
         Looking::Back => enigo.key_up(Key::S),
         Looking::Direction(_) => enigo.key_up(Key::A),
@@ -1,5 +1,6 @@
 use std::time::Instant;
 use tokio::time::sleep;
+use tracing::debug;
 
 use crate::{
     ace_state::AceState,
@@ -14,26 +15,26 @@ pub async fn handle_fly_horizontal(
     distance: f64,
 ) {
     if !ace_state.is_turning && !ace_state.is_moving {
-        let (delay_to_release, delay_to_fresh, key) = match instruction.looking {
-            Looking::Direction(_) => (TICKRATE_DURATION * 3, TICKRATE_DURATION * 12, Key::A),
+        let (delay_to_release, delay_to_fresh, key, key_name) = match instruction.looking {
+            Looking::Direction(_) => (TICKRATE_DURATION * 3, TICKRATE_DURATION * 12, Key::A, "a"),
 
             Looking::Back => {
                 if distance < 3.0 {
-                    (TICKRATE_DURATION * 3, TICKRATE_DURATION * 10, Key::S)
+                    (TICKRATE_DURATION * 3, TICKRATE_DURATION * 10, Key::S, "s")
                 } else {
-                    (TICKRATE_DURATION * 5, TICKRATE_DURATION * 10, Key::S)
+                    (TICKRATE_DURATION * 5, TICKRATE_DURATION * 10, Key::S, "s")
                 }
             }
 
             _ => {
                 if distance < 2.0 {
-                    (TICKRATE_DURATION, TICKRATE_DURATION * 10, Key::W)
+                    (TICKRATE_DURATION, TICKRATE_DURATION * 10, Key::W, "w")
                 } else if distance < 3.0 {
-                    (TICKRATE_DURATION * 2, TICKRATE_DURATION * 10, Key::W)
+                    (TICKRATE_DURATION * 2, TICKRATE_DURATION * 10, Key::W, "w")
                 } else if distance < 6.0 {
-                    (TICKRATE_DURATION * 3, TICKRATE_DURATION * 10, Key::W)
+                    (TICKRATE_DURATION * 3, TICKRATE_DURATION * 10, Key::W, "w")
                 } else {
-                    (TICKRATE_DURATION * 6, TICKRATE_DURATION * 10, Key::W)
+                    (TICKRATE_DURATION * 6, TICKRATE_DURATION * 10, Key::W, "w")
                 }
             }
         };
@@ -42,6 +43,8 @@ pub async fn handle_fly_horizontal(
         let is_fresh = elapsed >= delay_to_fresh;
 
         if is_fresh {
+            debug!(key = key_name, distance, hold = ?delay_to_release, "pulsing key");
+
             tokio::task::spawn(async move {
                 let mut enigo = Enigo::new();
 
@@ -56,6 +59,12 @@ pub async fn handle_fly_horizontal(
                 enigo.key_up(key);
             });
 
+            ace_state.pulse(key_name, delay_to_release);
+            ace_state.flight_pulses += 1;
+            if distance > 6.0 {
+                ace_state.pulse("control", delay_to_release);
+            }
+
             ace_state.last_flight = Instant::now();
         }
     }
@@ -75,7 +84,7 @@ pub async fn handle_fly_vertical(
             let is_fresh = elapsed >= TICKRATE_DURATION * 10;
 
             if is_fresh {
-                println!("UP {distance}");
+                debug!(distance, "flying up");
 
                 tokio::task::spawn(async move {
                     let mut enigo = Enigo::new();
@@ -90,6 +99,11 @@ pub async fn handle_fly_vertical(
                     enigo.key_up(Key::Space);
                 });
 
+                if distance >= 1.5 {
+                    ace_state.pulse("space", TICKRATE_DURATION * 5);
+                }
+
+                ace_state.flight_pulses += 1;
                 ace_state.last_space_press = Instant::now();
             };
         } else {
@@ -97,8 +111,9 @@ pub async fn handle_fly_vertical(
             let is_fresh = elapsed >= TICKRATE_DURATION * 10;
 
             if is_fresh {
-                println!("DOWN {distance}");
+                debug!(distance, "flying down");
                 enigo.key_click(Key::Shift);
+                ace_state.flight_pulses += 1;
                 ace_state.last_shift_press = Instant::now();
             }
         }
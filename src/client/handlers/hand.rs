@@ -1,30 +1,95 @@
+use std::time::Instant;
+
 use crate::{
-    ace_state::AceState,
+    ace_state::{AceState, SlotSwitch},
+    handlers::backend::InputBackend,
     instructions::{Action, Instruction},
     TICKRATE_DURATION,
 };
 
-use enigo::*;
+use enigo::{Key, MouseButton};
+use tracing::{debug, warn};
+
+/// Hotbar key presses made before giving up on a slot the telemetry never shows
+const SLOT_SWITCH_ATTEMPTS: u32 = 3;
+
+/// Presses the hotbar key of `slot` when another one is held, then waits for
+/// the telemetry to show the switch, pressing again if it does not. Without
+/// the held slot in the telemetry, the last key pressed is taken as held.
+pub fn select_hand_slot(
+    backend: &mut impl InputBackend,
+    ace_state: &mut AceState,
+    slot: char,
+    held_slot: Option<char>,
+) {
+    let held_slot = held_slot.or(ace_state.hand_slot);
+
+    if held_slot == Some(slot) {
+        if let Some(switch) = ace_state.slot_switch.take() {
+            debug!(slot = %slot, attempts = switch.attempts, "hand slot switched");
+        }
+
+        return;
+    }
+
+    let attempts = match &mut ace_state.slot_switch {
+        Some(switch) if switch.slot == slot => {
+            if switch.pressed_at.elapsed() < TICKRATE_DURATION * 10
+                || switch.attempts > SLOT_SWITCH_ATTEMPTS
+            {
+                return;
+            }
+
+            if switch.attempts == SLOT_SWITCH_ATTEMPTS {
+                warn!(slot = %slot, held = ?held_slot, "hand slot did not switch, giving up");
+                switch.attempts += 1;
+                ace_state.failed_slot_switch = Some((slot, held_slot));
+                return;
+            }
+
+            switch.attempts + 1
+        }
+
+        _ => 1,
+    };
+
+    debug!(slot = %slot, held = ?held_slot, attempts, "selecting hand slot");
+    backend.key_click(Key::Layout(slot));
+
+    ace_state.hand_slot = Some(slot);
+    ace_state.slot_switch = Some(SlotSwitch {
+        slot,
+        pressed_at: Instant::now(),
+        attempts,
+    });
+}
+
+pub async fn handle_hand(
+    backend: &mut impl InputBackend,
+    ace_state: &mut AceState,
+    instruction: Instruction,
+    held_slot: Option<char>,
+) {
+    select_hand_slot(backend, ace_state, instruction.hand_slot, held_slot);
 
-pub async fn handle_hand(enigo: &mut Enigo, ace_state: &mut AceState, instruction: Instruction) {
     if instruction.reset_hand_stack && !ace_state.hand_stack_reseted {
-        println!("Reseting Hand Stack");
-        enigo.key_click(Key::E);
+        debug!("resetting hand stack");
+        backend.key_click(Key::E);
         tokio::time::sleep(TICKRATE_DURATION * 2).await;
 
         for _ in 0..4 {
-            enigo.mouse_move_relative(0, 32);
+            backend.mouse_move_relative(0, 32);
             tokio::time::sleep(TICKRATE_DURATION).await;
         }
 
         tokio::time::sleep(TICKRATE_DURATION * 3).await;
-        enigo.mouse_click(MouseButton::Left);
+        backend.mouse_click(MouseButton::Left);
         tokio::time::sleep(TICKRATE_DURATION * 2).await;
-        enigo.mouse_click(MouseButton::Left);
+        backend.mouse_click(MouseButton::Left);
 
         tokio::time::sleep(TICKRATE_DURATION * 2).await;
-        enigo.mouse_click(MouseButton::Left);
-        enigo.key_click(Key::E);
+        backend.mouse_click(MouseButton::Left);
+        backend.key_click(Key::E);
         tokio::time::sleep(TICKRATE_DURATION).await;
         ace_state.hand_stack_reseted = true;
     }
@@ -32,10 +97,82 @@ pub async fn handle_hand(enigo: &mut Enigo, ace_state: &mut AceState, instructio
     match instruction.action {
         Action::RightClick => {
             if !ace_state.is_moving && !ace_state.is_turning {
-                enigo.mouse_click(MouseButton::Middle);
-                enigo.mouse_click(MouseButton::Right);
+                backend.mouse_click(MouseButton::Middle);
+                backend.mouse_click(MouseButton::Right);
+            }
+        }
+        Action::Mine => {
+            if !ace_state.is_pressing_left_click {
+                debug!("holding left click to mine");
+                backend.mouse_down(MouseButton::Left);
+                ace_state.is_pressing_left_click = true;
             }
         }
         _ => (),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        handlers::backend::mock::{Press, Recorder},
+        instructions::Looking,
+    };
+
+    fn mining(hand_slot: char) -> Instruction {
+        Instruction {
+            step: 0,
+            block: 0,
+            name: "test".to_owned(),
+            destination: [0.0; 3],
+            hand_slot,
+            reset_hand_stack: false,
+            looking: Looking::Front,
+            action: Action::Mine,
+            script: None,
+            control: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn holds_left_click_while_mining() {
+        let ace_state = AceState::new_shared();
+        let mut ace_state = ace_state.lock().await;
+        let mut recorder = Recorder::default();
+
+        handle_hand(&mut recorder, &mut ace_state, mining('2'), None).await;
+        handle_hand(&mut recorder, &mut ace_state, mining('2'), Some('2')).await;
+
+        assert_eq!(
+            recorder.presses,
+            [
+                Press::KeyClick(Key::Layout('2')),
+                Press::MouseDown(MouseButton::Left),
+            ]
+        );
+        assert!(ace_state.is_pressing_left_click);
+        assert!(ace_state.slot_switch.is_none());
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_a_slot_that_never_shows_up() {
+        let ace_state = AceState::new_shared();
+        let mut ace_state = ace_state.lock().await;
+        let mut recorder = Recorder::default();
+
+        for _ in 0..=SLOT_SWITCH_ATTEMPTS {
+            if let Some(switch) = &mut ace_state.slot_switch {
+                switch.pressed_at -= TICKRATE_DURATION * 10;
+            }
+
+            select_hand_slot(&mut recorder, &mut ace_state, '4', Some('1'));
+        }
+
+        assert_eq!(
+            recorder.presses,
+            vec![Press::KeyClick(Key::Layout('4')); SLOT_SWITCH_ATTEMPTS as usize]
+        );
+        assert_eq!(ace_state.failed_slot_switch, Some(('4', Some('1'))));
+    }
+}
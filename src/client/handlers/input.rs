@@ -0,0 +1,321 @@
+use std::{sync::Arc, time::Duration};
+
+use enigo::{Key, MouseButton};
+use futures_util::lock::Mutex;
+use tokio::time::sleep;
+use tracing::{debug, warn};
+
+use crate::{
+    ace_state::AceState,
+    handlers::{backend::InputBackend, hand::select_hand_slot},
+    instructions::{control::Control, Instruction},
+    TICKRATE_DURATION,
+};
+
+enum Input {
+    Key(Key),
+    Button(MouseButton),
+}
+
+/// Key or button of one of the inputs a plan may hold, which plans are checked
+/// against when they load
+fn input(name: &str) -> Option<Input> {
+    match name {
+        "w" => Some(Input::Key(Key::W)),
+        "a" => Some(Input::Key(Key::A)),
+        "s" => Some(Input::Key(Key::S)),
+        "d" => Some(Input::Key(Key::D)),
+        "space" => Some(Input::Key(Key::Space)),
+        "shift" => Some(Input::Key(Key::Shift)),
+        "control" => Some(Input::Key(Key::Control)),
+        "left_click" => Some(Input::Button(MouseButton::Left)),
+        "right_click" => Some(Input::Button(MouseButton::Right)),
+        _ => None,
+    }
+}
+
+fn press(backend: &mut impl InputBackend, name: &str) {
+    match input(name) {
+        Some(Input::Key(key)) => backend.key_down(key),
+        Some(Input::Button(button)) => backend.mouse_down(button),
+        None => warn!(input = name, "unknown input, not pressed"),
+    }
+}
+
+pub fn release(backend: &mut impl InputBackend, name: &str) {
+    match input(name) {
+        Some(Input::Key(key)) => backend.key_up(key),
+        Some(Input::Button(button)) => backend.mouse_up(button),
+        None => warn!(input = name, "unknown input, not released"),
+    }
+}
+
+/// Keeps the input down for the duration, the backend being free in between
+fn hold<B: InputBackend + Send + 'static>(
+    backend: &Arc<Mutex<B>>,
+    ace_state: &mut AceState,
+    name: &'static str,
+    duration: Duration,
+) {
+    debug!(input = name, hold = ?duration, "holding input");
+    let backend = backend.clone();
+
+    tokio::task::spawn(async move {
+        press(&mut *backend.lock().await, name);
+        sleep(duration).await;
+        release(&mut *backend.lock().await, name);
+    });
+
+    ace_state.pulse(name, duration);
+}
+
+/// Opens the chat, types the text and sends it, giving the chat box time to open
+async fn chat(backend: Arc<Mutex<impl InputBackend>>, text: String) {
+    backend.lock().await.key_click(Key::T);
+    sleep(TICKRATE_DURATION * 3).await;
+    backend.lock().await.key_sequence(&text);
+    sleep(TICKRATE_DURATION).await;
+    backend.lock().await.key_click(Key::Return);
+}
+
+/// Presses what a hold, click, drop, swap_hands, select or chat step asks for.
+/// Holds and chats carry on in a task of their own once this returns.
+pub async fn handle_input<B: InputBackend + Send + 'static>(
+    backend: &Arc<Mutex<B>>,
+    ace_state: &mut AceState,
+    instruction: Instruction,
+    held_slot: Option<char>,
+) {
+    let Some(control) = instruction.control else {
+        return;
+    };
+
+    match control {
+        Control::Hold { input, duration } => hold(backend, ace_state, input, duration),
+
+        Control::Chat(text) => {
+            debug!(%text, "sending chat");
+
+            tokio::task::spawn(chat(backend.clone(), text));
+
+            ace_state.pulse("chat", TICKRATE_DURATION * 4);
+        }
+
+        control => press_control(&mut *backend.lock().await, ace_state, control, held_slot),
+    }
+}
+
+/// Steps pressing a single key or button, done right away
+fn press_control(
+    backend: &mut impl InputBackend,
+    ace_state: &mut AceState,
+    control: Control,
+    held_slot: Option<char>,
+) {
+    match control {
+        Control::Click(name) => {
+            debug!(input = name, "clicking");
+
+            if let Some(Input::Button(button)) = input(name) {
+                backend.mouse_click(button);
+            }
+        }
+
+        Control::Drop { stack } => {
+            debug!(stack, "dropping the held item");
+
+            if stack {
+                backend.key_down(Key::Control);
+            }
+
+            backend.key_click(Key::Q);
+
+            if stack {
+                backend.key_up(Key::Control);
+            }
+        }
+
+        Control::SwapHands => {
+            debug!("swapping hands");
+            backend.key_click(Key::F);
+        }
+
+        // the hand handler checks the switch on the steps after it
+        Control::Select(slot) => {
+            select_hand_slot(backend, ace_state, char::from(b'0' + slot), held_slot);
+        }
+
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        handlers::backend::mock::{Press, Recorder},
+        instructions::{control::INPUTS, Action, Looking},
+    };
+
+    fn step(control: Control) -> Instruction {
+        Instruction {
+            step: 0,
+            block: 0,
+            name: "test".to_owned(),
+            destination: [0.0; 3],
+            hand_slot: '1',
+            reset_hand_stack: false,
+            looking: Looking::Front,
+            action: Action::Walk,
+            script: None,
+            control: Some(control),
+        }
+    }
+
+    /// Presses made by the step, waiting for the tasks of holds and chats
+    async fn presses(
+        ace_state: &mut AceState,
+        control: Control,
+        held_slot: Option<char>,
+        wait: Duration,
+    ) -> Vec<Press> {
+        let backend = Arc::new(Mutex::new(Recorder::default()));
+        handle_input(&backend, ace_state, step(control), held_slot).await;
+        sleep(wait).await;
+
+        let presses = backend.lock().await.presses.clone();
+        presses
+    }
+
+    async fn presses_now(ace_state: &mut AceState, control: Control) -> Vec<Press> {
+        presses(ace_state, control, None, Duration::ZERO).await
+    }
+
+    #[tokio::test]
+    async fn clicks_the_button_asked_for() {
+        let ace_state = AceState::new_shared();
+        let mut ace_state = ace_state.lock().await;
+
+        assert_eq!(
+            presses_now(&mut ace_state, Control::Click("left_click")).await,
+            [Press::MouseClick(MouseButton::Left)]
+        );
+        assert_eq!(
+            presses_now(&mut ace_state, Control::Click("right_click")).await,
+            [Press::MouseClick(MouseButton::Right)]
+        );
+    }
+
+    #[tokio::test]
+    async fn drops_an_item_or_the_whole_stack() {
+        let ace_state = AceState::new_shared();
+        let mut ace_state = ace_state.lock().await;
+
+        assert_eq!(
+            presses_now(&mut ace_state, Control::Drop { stack: false }).await,
+            [Press::KeyClick(Key::Q)]
+        );
+        assert_eq!(
+            presses_now(&mut ace_state, Control::Drop { stack: true }).await,
+            [
+                Press::KeyDown(Key::Control),
+                Press::KeyClick(Key::Q),
+                Press::KeyUp(Key::Control),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn swaps_hands() {
+        let ace_state = AceState::new_shared();
+        let mut ace_state = ace_state.lock().await;
+
+        assert_eq!(
+            presses_now(&mut ace_state, Control::SwapHands).await,
+            [Press::KeyClick(Key::F)]
+        );
+    }
+
+    #[tokio::test]
+    async fn selects_a_slot_unless_it_is_held() {
+        let ace_state = AceState::new_shared();
+        let mut ace_state = ace_state.lock().await;
+
+        assert_eq!(
+            presses_now(&mut ace_state, Control::Select(3)).await,
+            [Press::KeyClick(Key::Layout('3'))]
+        );
+        assert_eq!(ace_state.hand_slot, Some('3'));
+
+        assert_eq!(presses_now(&mut ace_state, Control::Select(3)).await, []);
+    }
+
+    #[tokio::test]
+    async fn selects_by_the_slot_the_telemetry_shows_held() {
+        let ace_state = AceState::new_shared();
+        let mut ace_state = ace_state.lock().await;
+        ace_state.hand_slot = Some('3');
+
+        assert_eq!(
+            presses(
+                &mut ace_state,
+                Control::Select(3),
+                Some('5'),
+                Duration::ZERO
+            )
+            .await,
+            [Press::KeyClick(Key::Layout('3'))]
+        );
+        assert_eq!(
+            presses(
+                &mut ace_state,
+                Control::Select(5),
+                Some('5'),
+                Duration::ZERO
+            )
+            .await,
+            []
+        );
+    }
+
+    #[tokio::test]
+    async fn holds_an_input_for_its_duration() {
+        let ace_state = AceState::new_shared();
+        let mut ace_state = ace_state.lock().await;
+        let hold = Control::Hold {
+            input: "space",
+            duration: Duration::from_millis(10),
+        };
+
+        assert_eq!(
+            presses(&mut ace_state, hold, None, Duration::from_millis(100)).await,
+            [Press::KeyDown(Key::Space), Press::KeyUp(Key::Space)]
+        );
+        assert_eq!(ace_state.active_inputs(), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn knows_every_input_a_plan_may_hold() {
+        for name in INPUTS {
+            assert!(input(name).is_some(), "{name}");
+        }
+
+        assert!(input("contrl").is_none());
+    }
+
+    #[tokio::test]
+    async fn types_and_sends_a_chat_message() {
+        let ace_state = AceState::new_shared();
+        let mut ace_state = ace_state.lock().await;
+        let chat = Control::Chat("/home base".to_owned());
+
+        assert_eq!(
+            presses(&mut ace_state, chat, None, TICKRATE_DURATION * 6).await,
+            [
+                Press::KeyClick(Key::T),
+                Press::Text("/home base".to_owned()),
+                Press::KeyClick(Key::Return),
+            ]
+        );
+    }
+}
@@ -9,7 +9,11 @@ pub enum Message {
         vertical_force: i32,
     },
 
-    Hand(Instruction),
+    Hand {
+        instruction: Instruction,
+        /// Hotbar slot the telemetry shows held, if it tells
+        held_slot: Option<char>,
+    },
 
     FlyHorizontal {
         instruction: Instruction,
@@ -20,4 +24,10 @@ pub enum Message {
         instruction: Instruction,
         distance: f64,
     },
+
+    Input {
+        instruction: Instruction,
+        /// Hotbar slot the telemetry shows held, for a select step
+        held_slot: Option<char>,
+    },
 }
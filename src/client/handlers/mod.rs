@@ -2,4 +2,6 @@ pub mod message;
 pub mod head_movement;
 pub mod hand;
 pub mod fly;
-pub mod finished;
\ No newline at end of file
+pub mod finished;
+pub mod input;
+pub mod backend;
\ No newline at end of file
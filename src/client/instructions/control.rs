@@ -0,0 +1,262 @@
+use std::{fmt, time::Duration};
+
+use serde_json::Value as Json;
+
+use crate::{minecraft_resource::MinecraftResource, TICKRATE_DURATION};
+
+use super::expand::{evaluate, Variables};
+
+/// How long a `wait_until` step waits when it does not give a `timeout`
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Keys and buttons a `hold` step can press, named as in the status frame
+pub const INPUTS: [&str; 9] = [
+    "w",
+    "a",
+    "s",
+    "d",
+    "space",
+    "shift",
+    "control",
+    "left_click",
+    "right_click",
+];
+
+/// Time a single input is given to land before the next step starts
+const INPUT_TIME: Duration = TICKRATE_DURATION.saturating_mul(3);
+
+/// Time to open the chat, type and send, the typing itself being near instant
+const CHAT_TIME: Duration = TICKRATE_DURATION.saturating_mul(10);
+
+/// A step that does something other than heading for its destination
+#[derive(Debug, Clone)]
+pub enum Control {
+    /// Stays where it is for a while
+    Wait(Duration),
+    /// Holds a key or a mouse button down for a while, without moving
+    Hold {
+        input: &'static str,
+        duration: Duration,
+    },
+    /// Stays where it is until the condition holds, giving up after the timeout
+    WaitUntil {
+        condition: Condition,
+        timeout: Duration,
+    },
+    /// Runs the `then_steps` steps after it when the condition holds, else the
+    /// `else_steps` steps after those
+    If {
+        condition: Condition,
+        then_steps: usize,
+        else_steps: usize,
+    },
+    /// A single `left_click` or `right_click`
+    Click(&'static str),
+    /// Drops the held item, or the whole stack
+    Drop { stack: bool },
+    /// Swaps the items of the main hand and the off hand
+    SwapHands,
+    /// Selects a hotbar slot, 1 to 9
+    Select(u8),
+    /// Opens the chat, types the text and sends it
+    Chat(String),
+}
+
+impl Control {
+    /// Name used for this step in plan files
+    pub fn name(&self) -> &'static str {
+        match self {
+            Control::Wait(_) => "wait",
+            Control::Hold { .. } => "hold",
+            Control::WaitUntil { .. } => "wait_until",
+            Control::If { .. } => "if",
+            Control::Click(_) => "click",
+            Control::Drop { .. } => "drop",
+            Control::SwapHands => "swap_hands",
+            Control::Select(_) => "select",
+            Control::Chat(_) => "chat",
+        }
+    }
+
+    /// How long the step takes, unless it depends on the telemetry
+    pub fn duration(&self) -> Option<Duration> {
+        match self {
+            Control::Wait(duration) | Control::Hold { duration, .. } => Some(*duration),
+            Control::WaitUntil { .. } | Control::If { .. } => None,
+            Control::Chat(_) => Some(CHAT_TIME),
+            Control::Click(_) | Control::Drop { .. } | Control::SwapHands | Control::Select(_) => {
+                Some(INPUT_TIME)
+            }
+        }
+    }
+
+    /// Whether the step presses something, the handlers doing it when it starts
+    pub fn is_input(&self) -> bool {
+        !matches!(
+            self,
+            Control::Wait(_) | Control::WaitUntil { .. } | Control::If { .. }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+/// Longer operators first, so `<=` is not read as `<`
+const COMPARISONS: [(&str, Comparison); 6] = [
+    ("<=", Comparison::LessOrEqual),
+    (">=", Comparison::GreaterOrEqual),
+    ("==", Comparison::Equal),
+    ("!=", Comparison::NotEqual),
+    ("<", Comparison::Less),
+    (">", Comparison::Greater),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expected {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+}
+
+/// `<field> <comparison> <value>` over the telemetry, such as `y < 60` or
+/// `dimension == "nether"`. `x`, `y`, `z`, `yaw` and `pitch` name the player
+/// position and head, anything else a field of the telemetry frame, with
+/// dots to reach into objects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    field: String,
+    comparison: Comparison,
+    expected: Expected,
+}
+
+impl Condition {
+    /// Numbers may be expressions over the variables in scope
+    pub fn parse(text: &str, variables: &Variables) -> Result<Self, String> {
+        let (index, operator, comparison) = text
+            .char_indices()
+            .find_map(|(index, _)| {
+                COMPARISONS
+                    .iter()
+                    .find(|(operator, _)| text[index..].starts_with(operator))
+                    .map(|(operator, comparison)| (index, *operator, *comparison))
+            })
+            .ok_or_else(|| format!("{text:?} compares nothing, use one of < <= > >= == !="))?;
+
+        let field = text[..index].trim();
+        let value = text[index + operator.len()..].trim();
+
+        let is_field = !field.is_empty()
+            && field
+                .split('.')
+                .all(|part| !part.is_empty() && part.chars().all(is_field_char));
+
+        if !is_field {
+            return Err(format!("{text:?} should start with a telemetry field"));
+        }
+
+        let quoted = ['"', '\''].into_iter().find_map(|quote| {
+            value
+                .strip_prefix(quote)
+                .and_then(|value| value.strip_suffix(quote))
+        });
+
+        let expected = match (quoted, value) {
+            (Some(text), _) => Expected::Text(text.to_owned()),
+            (None, "true") => Expected::Bool(true),
+            (None, "false") => Expected::Bool(false),
+            (None, expression) => Expected::Number(
+                evaluate(expression, variables).map_err(|error| format!("{text:?}: {error}"))?,
+            ),
+        };
+
+        let orders = matches!(
+            comparison,
+            Comparison::Less
+                | Comparison::LessOrEqual
+                | Comparison::Greater
+                | Comparison::GreaterOrEqual
+        );
+
+        if orders && !matches!(expected, Expected::Number(_)) {
+            return Err(format!("{text:?}: {operator} only compares numbers"));
+        }
+
+        Ok(Self {
+            field: field.to_owned(),
+            comparison,
+            expected,
+        })
+    }
+
+    /// Whether the condition holds for the latest telemetry, an error when the
+    /// field is missing or of another type than the value it is compared to
+    pub fn holds(&self, resource: &MinecraftResource) -> Result<bool, String> {
+        let value = resource
+            .field(&self.field)
+            .ok_or_else(|| format!("the telemetry has no {}", self.field))?;
+
+        let ordering = match (&self.expected, &value) {
+            (Expected::Number(expected), value) if value.is_number() => {
+                value.as_f64().unwrap_or(f64::NAN).partial_cmp(expected)
+            }
+            (Expected::Text(expected), Json::String(value)) => Some(value.as_str().cmp(expected)),
+            (Expected::Bool(expected), Json::Bool(value)) => Some(value.cmp(expected)),
+            _ => {
+                return Err(format!(
+                    "{} is {value}, which {self} cannot compare",
+                    self.field
+                ))
+            }
+        };
+
+        let Some(ordering) = ordering else {
+            return Ok(false);
+        };
+
+        Ok(match self.comparison {
+            Comparison::Less => ordering.is_lt(),
+            Comparison::LessOrEqual => ordering.is_le(),
+            Comparison::Greater => ordering.is_gt(),
+            Comparison::GreaterOrEqual => ordering.is_ge(),
+            Comparison::Equal => ordering.is_eq(),
+            Comparison::NotEqual => ordering.is_ne(),
+        })
+    }
+}
+
+fn is_field_char(char: char) -> bool {
+    char.is_ascii_alphanumeric() || char == '_'
+}
+
+/// Written the way it parses back, with expressions already evaluated
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (operator, _) = COMPARISONS
+            .iter()
+            .find(|(_, comparison)| *comparison == self.comparison)
+            .unwrap();
+
+        match &self.expected {
+            Expected::Number(number) => write!(f, "{} {operator} {number}", self.field),
+            Expected::Text(text) => write!(f, "{} {operator} '{text}'", self.field),
+            Expected::Bool(bool) => write!(f, "{} {operator} {bool}", self.field),
+        }
+    }
+}
+
+/// `ms` of a `wait` or a `hold`, or the `timeout` of a `wait_until`, already
+/// evaluated to a number of milliseconds
+pub fn milliseconds(value: f64, key: &str) -> Result<Duration, String> {
+    match value.is_finite() && value >= 0.0 {
+        true => Ok(Duration::from_secs_f64(value / 1000.0)),
+        false => Err(format!("{key} expects milliseconds, got {value}")),
+    }
+}
@@ -0,0 +1,540 @@
+use std::{collections::BTreeMap, iter::Peekable, str::Chars};
+
+use toml::{map::Map, Value};
+
+use super::{control::Condition, include::Plan, utils::as_number};
+
+/// Name to value of the variables in scope
+pub type Variables = BTreeMap<String, f64>;
+
+/// Loop counter name when a repeat block does not give one
+const DEFAULT_COUNTER: &str = "i";
+
+/// Most times a single repeat block runs its path, so a typo in the count
+/// does not spin on a path with no steps
+const MAX_REPEAT: u64 = 10_000;
+
+/// Most steps a path expands to, nested repeats and segments multiplied
+/// through, so a plan cannot grow until the memory runs out
+const MAX_STEPS: usize = 100_000;
+
+/// Keys an expanded `if` entry counts the steps of its branches with, the
+/// branches following it in the path
+pub const THEN_STEPS: &str = "then_steps";
+pub const ELSE_STEPS: &str = "else_steps";
+
+/// Keys of path entries that hold milliseconds
+const DURATIONS: [&str; 3] = ["wait", "ms", "timeout"];
+
+/// Keys of path entries that hold a condition
+const CONDITIONS: [&str; 2] = ["wait_until", "if"];
+
+/// Named positions and paths a plan file declares for all its blocks
+pub struct Library<'a> {
+    plan: &'a Plan,
+    waypoints: BTreeMap<String, [f64; 3]>,
+    segments: Option<&'a Map<String, Value>>,
+}
+
+impl<'a> Library<'a> {
+    /// The `[waypoints]` and `[segments]` tables of a plan, waypoints able to
+    /// use the plan variables
+    pub fn new(plan: &'a Plan, variables: &Variables) -> Result<Self, String> {
+        let mut waypoints = BTreeMap::new();
+
+        if let Some(declared) = plan.table.get("waypoints") {
+            let declared = declared.as_table().ok_or_else(|| {
+                format!("{}: waypoints should be a table, got {declared}", plan.file)
+            })?;
+
+            for (name, value) in declared {
+                let position = position(value, variables).map_err(|error| {
+                    let file = plan.file_of("waypoints", name);
+                    format!("{file}: waypoint {name}: {error}")
+                })?;
+
+                waypoints.insert(name.to_owned(), position);
+            }
+        }
+
+        let segments = match plan.table.get("segments") {
+            Some(Value::Table(segments)) => Some(segments),
+            Some(segments) => {
+                return Err(format!(
+                    "{}: segments should be a table, got {segments}",
+                    plan.file
+                ))
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            plan,
+            waypoints,
+            segments,
+        })
+    }
+
+    pub fn waypoint(&self, name: &str) -> Option<[f64; 3]> {
+        self.waypoints.get(name).copied()
+    }
+
+    pub fn segment(&self, name: &str) -> Option<&'a [Value]> {
+        self.segments?
+            .get(name)
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+    }
+}
+
+/// The `variables` table of a plan, a block or a repeat, added to the ones
+/// already in scope. Values are numbers or expressions over other variables,
+/// in any order.
+pub fn variables(table: &Map<String, Value>, scope: &Variables) -> Result<Variables, String> {
+    let mut variables = scope.clone();
+
+    let Some(declared) = table.get("variables") else {
+        return Ok(variables);
+    };
+
+    let mut pending: Vec<(&String, &Value)> = declared
+        .as_table()
+        .ok_or_else(|| format!("variables should be a table, got {declared}"))?
+        .iter()
+        .collect();
+
+    // resolve whatever only needs known variables, until nothing is left
+    while !pending.is_empty() {
+        let mut errors = Vec::new();
+        let count = pending.len();
+
+        pending.retain(|(name, value)| match number(value, &variables) {
+            Ok(number) => {
+                variables.insert(name.to_string(), number);
+                false
+            }
+            Err(error) => {
+                errors.push(format!("{name}: {error}"));
+                true
+            }
+        });
+
+        if pending.len() == count {
+            return Err(errors.join(", "));
+        }
+    }
+
+    Ok(variables)
+}
+
+/// Path entries with every `repeat` unrolled, every `segment` pasted in and
+/// every destination three numbers. `including` lists the segments being
+/// expanded, outermost first.
+pub fn expand_path(
+    path: &[Value],
+    variables: &Variables,
+    library: &Library,
+    including: &[&str],
+) -> Result<Vec<Map<String, Value>>, String> {
+    let mut expanded = Vec::new();
+
+    for value in path {
+        let item = value
+            .as_table()
+            .ok_or_else(|| format!("path entries should be tables, got {value}"))?;
+
+        if let Some(repeat) = item.get("repeat") {
+            expanded.extend(expand_repeat(item, repeat, variables, library, including)?);
+        } else if let Some(segment) = item.get("segment") {
+            expanded.extend(expand_segment(segment, variables, library, including)?);
+        } else if item.contains_key("if") {
+            expanded.extend(expand_if(item, variables, library, including)?);
+        } else {
+            expanded.push(resolve_item(item, variables, library)?);
+        }
+
+        check_length(&expanded)?;
+    }
+
+    Ok(expanded)
+}
+
+fn check_length(expanded: &[Map<String, Value>]) -> Result<(), String> {
+    match expanded.len() > MAX_STEPS {
+        true => Err(format!("path expands to more than {MAX_STEPS} steps")),
+        false => Ok(()),
+    }
+}
+
+/// `{ repeat = 12, counter = "i", path = [...] }`, the counter going from 0 to
+/// one less than the count
+fn expand_repeat(
+    item: &Map<String, Value>,
+    repeat: &Value,
+    variables: &Variables,
+    library: &Library,
+    including: &[&str],
+) -> Result<Vec<Map<String, Value>>, String> {
+    let count = number(repeat, variables).map_err(|error| format!("repeat: {error}"))?;
+
+    if count < 0.0 || count.fract() != 0.0 {
+        return Err(format!("repeat expects a whole count, got {count}"));
+    }
+
+    if count > MAX_REPEAT as f64 {
+        return Err(format!("repeat {count} exceeds the limit of {MAX_REPEAT}"));
+    }
+
+    let counter = item
+        .get("counter")
+        .and_then(Value::as_str)
+        .unwrap_or(DEFAULT_COUNTER);
+
+    let path = item
+        .get("path")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "repeat needs a path".to_owned())?;
+
+    let mut expanded = Vec::new();
+
+    for index in 0..count as u64 {
+        let mut scope = variables.clone();
+        scope.insert(counter.to_owned(), index as f64);
+
+        let scope = self::variables(item, &scope)?;
+        expanded.extend(expand_path(path, &scope, library, including)?);
+        check_length(&expanded)?;
+    }
+
+    Ok(expanded)
+}
+
+/// `{ if = "y < 60", then = [...], else = [...] }`, kept as one entry counting
+/// the steps of each branch, followed by both branches. Which one runs is only
+/// known once the condition is checked on the telemetry.
+fn expand_if(
+    item: &Map<String, Value>,
+    variables: &Variables,
+    library: &Library,
+    including: &[&str],
+) -> Result<Vec<Map<String, Value>>, String> {
+    let mut branches = Vec::new();
+
+    for key in ["then", "else"] {
+        let path = match item.get(key) {
+            None => &[][..],
+            Some(Value::Array(path)) => path.as_slice(),
+            Some(path) => return Err(format!("{key} expects a path, got {path}")),
+        };
+
+        branches.push(expand_path(path, variables, library, including)?);
+    }
+
+    let mut entry = item.clone();
+    entry.remove("then");
+    entry.remove("else");
+    entry.insert(
+        THEN_STEPS.to_owned(),
+        Value::Integer(branches[0].len() as i64),
+    );
+    entry.insert(
+        ELSE_STEPS.to_owned(),
+        Value::Integer(branches[1].len() as i64),
+    );
+
+    let mut expanded = vec![resolve_item(&entry, variables, library)?];
+    expanded.extend(branches.into_iter().flatten());
+
+    Ok(expanded)
+}
+
+/// `{ segment = "deposit" }`, the steps of `[segments] deposit = [...]`
+fn expand_segment(
+    segment: &Value,
+    variables: &Variables,
+    library: &Library,
+    including: &[&str],
+) -> Result<Vec<Map<String, Value>>, String> {
+    let name = segment
+        .as_str()
+        .ok_or_else(|| format!("segment expects a name, got {segment}"))?;
+
+    if including.contains(&name) {
+        return Err(format!("loops back to segment {name}"));
+    }
+
+    let path = library
+        .segment(name)
+        .ok_or_else(|| format!("unknown segment {name}"))?;
+
+    expand_path(path, variables, library, &[including, &[name]].concat()).map_err(|error| {
+        match library.plan.file_of("segments", name) {
+            file if file == library.plan.file => format!("segment {name}: {error}"),
+            file => format!("segment {name} in {file}: {error}"),
+        }
+    })
+}
+
+fn resolve_item(
+    item: &Map<String, Value>,
+    variables: &Variables,
+    library: &Library,
+) -> Result<Map<String, Value>, String> {
+    let mut item = item.clone();
+
+    if let Some(destination) = item.get("destination") {
+        let [x, y, z] = match destination.as_str() {
+            Some(name) => library
+                .waypoint(name)
+                .ok_or_else(|| format!("unknown waypoint {name}"))?,
+            None => position(destination, variables)?,
+        };
+
+        item.insert(
+            "destination".to_owned(),
+            Value::Array(vec![Value::Float(x), Value::Float(y), Value::Float(z)]),
+        );
+    }
+
+    for key in DURATIONS {
+        if let Some(value) = item.get(key) {
+            let value = number(value, variables).map_err(|error| format!("{key}: {error}"))?;
+            item.insert(key.to_owned(), Value::Float(value));
+        }
+    }
+
+    // conditions are checked while running, so only their values are
+    // evaluated now
+    for key in CONDITIONS {
+        if let Some(Value::String(text)) = item.get(key) {
+            let condition = Condition::parse(text, variables)?;
+            item.insert(key.to_owned(), Value::String(condition.to_string()));
+        }
+    }
+
+    Ok(item)
+}
+
+/// `[x, y, z]` of numbers or expressions
+fn position(value: &Value, variables: &Variables) -> Result<[f64; 3], String> {
+    let coords = value
+        .as_array()
+        .filter(|coords| coords.len() == 3)
+        .ok_or_else(|| format!("expected [x, y, z], got {value}"))?;
+
+    Ok([
+        number(&coords[0], variables)?,
+        number(&coords[1], variables)?,
+        number(&coords[2], variables)?,
+    ])
+}
+
+/// A number as is, or a string evaluated as an expression
+pub(super) fn number(value: &Value, variables: &Variables) -> Result<f64, String> {
+    if let Some(number) = as_number(value) {
+        return Ok(number);
+    }
+
+    match value.as_str() {
+        Some(expression) => evaluate(expression, variables),
+        None => Err(format!("expected a number or an expression, got {value}")),
+    }
+}
+
+/// Evaluates `+ - * / %`, parentheses, numbers and variable names, with the
+/// usual precedence. `%` never gives a negative remainder.
+pub fn evaluate(expression: &str, variables: &Variables) -> Result<f64, String> {
+    let mut parser = Parser {
+        chars: expression.chars().peekable(),
+        variables,
+    };
+
+    parser
+        .sum()
+        .and_then(|result| match parser.peek() {
+            None => Ok(result),
+            Some(char) => Err(format!("unexpected {char:?}")),
+        })
+        .map_err(|error| format!("{error} in {expression:?}"))
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    variables: &'a Variables,
+}
+
+impl Parser<'_> {
+    /// Next character that is not a space, left unconsumed
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.next_if(|char| char.is_whitespace()).is_some() {}
+        self.chars.peek().copied()
+    }
+
+    fn sum(&mut self) -> Result<f64, String> {
+        let mut result = self.product()?;
+
+        loop {
+            match self.peek() {
+                Some('+') => {
+                    self.chars.next();
+                    result += self.product()?;
+                }
+                Some('-') => {
+                    self.chars.next();
+                    result -= self.product()?;
+                }
+                _ => return Ok(result),
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<f64, String> {
+        let mut result = self.unary()?;
+
+        loop {
+            match self.peek() {
+                Some('*') => {
+                    self.chars.next();
+                    result *= self.unary()?;
+                }
+                Some('/') => {
+                    self.chars.next();
+                    result /= self.divisor('/')?;
+                }
+                Some('%') => {
+                    self.chars.next();
+                    result = result.rem_euclid(self.divisor('%')?);
+                }
+                _ => return Ok(result),
+            }
+        }
+    }
+
+    fn divisor(&mut self, operator: char) -> Result<f64, String> {
+        let divisor = self.unary()?;
+
+        match divisor == 0.0 {
+            true => Err(format!("{operator} by zero")),
+            false => Ok(divisor),
+        }
+    }
+
+    fn unary(&mut self) -> Result<f64, String> {
+        match self.peek() {
+            Some('-') => {
+                self.chars.next();
+                Ok(-self.unary()?)
+            }
+            Some('+') => {
+                self.chars.next();
+                self.unary()
+            }
+            _ => self.atom(),
+        }
+    }
+
+    fn atom(&mut self) -> Result<f64, String> {
+        match self.peek() {
+            Some('(') => {
+                self.chars.next();
+                let result = self.sum()?;
+
+                match self.peek() {
+                    Some(')') => {
+                        self.chars.next();
+                        Ok(result)
+                    }
+                    _ => Err("missing )".to_owned()),
+                }
+            }
+
+            Some(char) if char.is_ascii_digit() || char == '.' => {
+                let mut number = String::new();
+
+                while let Some(char) = self
+                    .chars
+                    .next_if(|char| char.is_ascii_digit() || *char == '.')
+                {
+                    number.push(char);
+                }
+
+                number
+                    .parse::<f64>()
+                    .map_err(|_| format!("bad number {number}"))
+            }
+
+            Some(char) if char.is_alphabetic() || char == '_' => {
+                let mut name = String::new();
+
+                while let Some(char) = self
+                    .chars
+                    .next_if(|char| char.is_alphanumeric() || *char == '_')
+                {
+                    name.push(char);
+                }
+
+                self.variables
+                    .get(&name)
+                    .copied()
+                    .ok_or_else(|| format!("unknown variable {name}"))
+            }
+
+            Some(char) => Err(format!("unexpected {char:?}")),
+            None => Err("unexpected end".to_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Steps the path of the first block of a plan expands to
+    fn expand(source: &str) -> Result<usize, String> {
+        let plan = Plan::parse("test.toml", source)?;
+        let library = Library::new(&plan, &Variables::new())?;
+        let path = plan.table["instruction"][0]["path"].as_array().unwrap();
+
+        expand_path(path, &Variables::new(), &library, &[]).map(|expanded| expanded.len())
+    }
+
+    #[test]
+    fn multiplies_nested_repeats() {
+        let source = r#"
+            [[instruction]]
+            path = [{ repeat = 3, path = [{ repeat = 4, path = [{ destination = [0, 0, 0] }] }] }]
+        "#;
+
+        assert_eq!(expand(source), Ok(12));
+    }
+
+    #[test]
+    fn caps_the_steps_of_nested_repeats() {
+        let source = r#"
+            [[instruction]]
+            path = [
+                { repeat = 10000, path = [
+                    { repeat = 10000, path = [{ destination = [0, 0, 0] }] },
+                ] },
+            ]
+        "#;
+
+        assert_eq!(
+            expand(source),
+            Err(format!("path expands to more than {MAX_STEPS} steps"))
+        );
+    }
+
+    #[test]
+    fn caps_a_single_repeat() {
+        let source = r#"
+            [[instruction]]
+            path = [{ repeat = 10001, path = [] }]
+        "#;
+
+        assert_eq!(
+            expand(source),
+            Err(format!("repeat 10001 exceeds the limit of {MAX_REPEAT}"))
+        );
+    }
+}
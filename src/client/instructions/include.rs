@@ -0,0 +1,209 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use toml::{map::Map, Value};
+
+/// Tables merged key by key, the including file winning over what it includes
+/// and a later include over an earlier one
+const MERGED_TABLES: [&str; 4] = ["default", "variables", "waypoints", "segments"];
+
+/// A plan file with the files it includes merged in
+#[derive(Default)]
+pub struct Plan {
+    /// File the plan was loaded from, named in errors about the plan as a whole
+    pub file: String,
+    pub table: Map<String, Value>,
+    /// File each `[[instruction]]` block was written in
+    pub block_files: Vec<String>,
+    /// File each waypoint and segment was written in, by table then name
+    pub defined_in: BTreeMap<(&'static str, String), String>,
+    /// Script files of the plan and its includes, relative to the working
+    /// directory
+    pub scripts: Vec<PathBuf>,
+    /// Files merged in so far, each only once however many files include it
+    loaded: Vec<PathBuf>,
+}
+
+impl Plan {
+    /// A plan with no includes, such as the one built into the binary
+    pub fn parse(file: &str, source: &str) -> Result<Self, String> {
+        let table: Map<String, Value> =
+            toml::from_str(source).map_err(|error| format!("{file}: {error}"))?;
+
+        let mut plan = Plan {
+            file: file.to_owned(),
+            ..Plan::default()
+        };
+
+        plan.merge(file, Path::new(""), table);
+        Ok(plan)
+    }
+
+    /// File that defines a waypoint or a segment, or else the plan file
+    pub fn file_of(&self, table: &'static str, name: &str) -> &str {
+        self.defined_in
+            .get(&(table, name.to_owned()))
+            .unwrap_or(&self.file)
+    }
+
+    /// `directory` is where the file is, which its script paths are relative to
+    fn merge(&mut self, file: &str, directory: &Path, mut table: Map<String, Value>) {
+        let scripts = match table.remove("scripts") {
+            None => Vec::new(),
+            Some(Value::Array(scripts)) => scripts,
+            Some(script) => vec![script],
+        };
+
+        for script in scripts.iter().filter_map(Value::as_str) {
+            let script = directory.join(script);
+
+            if !self.scripts.contains(&script) {
+                self.scripts.push(script);
+            }
+        }
+
+        for key in MERGED_TABLES {
+            let Some(Value::Table(entries)) = table.remove(key) else {
+                continue;
+            };
+
+            let merged = self
+                .table
+                .entry(key)
+                .or_insert_with(|| Value::Table(Map::new()));
+
+            for (name, value) in entries {
+                if key == "waypoints" || key == "segments" {
+                    self.defined_in.insert((key, name.clone()), file.to_owned());
+                }
+
+                if let Value::Table(merged) = merged {
+                    merged.insert(name, value);
+                }
+            }
+        }
+
+        if let Some(Value::Array(blocks)) = table.remove("instruction") {
+            let count = self.block_files.len() + blocks.len();
+            self.block_files.resize(count, file.to_owned());
+
+            match self.table.get_mut("instruction") {
+                Some(Value::Array(merged)) => merged.extend(blocks),
+                _ => {
+                    self.table
+                        .insert("instruction".to_owned(), Value::Array(blocks));
+                }
+            }
+        }
+
+        self.table.extend(table);
+    }
+}
+
+/// Loads a plan file and, first, the files its `include = [...]` lists.
+/// Included paths are relative to the file naming them, and a file included
+/// from several places is merged the first time only.
+pub fn load(path: &str) -> Result<Plan, String> {
+    let mut plan = Plan {
+        file: path.to_owned(),
+        ..Plan::default()
+    };
+
+    load_into(&mut plan, Path::new(path), &mut Vec::new())?;
+    Ok(plan)
+}
+
+fn load_into(plan: &mut Plan, path: &Path, including: &mut Vec<PathBuf>) -> Result<(), String> {
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+
+    let file = match including.is_empty() {
+        true => plan.file.clone(),
+        false => display(&canonical),
+    };
+
+    if including.contains(&canonical) {
+        let chain: Vec<String> = including
+            .iter()
+            .chain([&canonical])
+            .map(|path| display(path))
+            .collect();
+
+        return Err(format!("{file}: includes itself: {}", chain.join(" -> ")));
+    }
+
+    if plan.loaded.contains(&canonical) {
+        return Ok(());
+    }
+
+    let source =
+        fs::read_to_string(path).map_err(|error| format!("could not read {file}: {error}"))?;
+
+    let mut table: Map<String, Value> =
+        toml::from_str(&source).map_err(|error| format!("{file}: {error}"))?;
+
+    let includes = match table.remove("include") {
+        None => Vec::new(),
+        Some(Value::Array(includes)) => includes,
+        Some(include) => vec![include],
+    };
+
+    including.push(canonical.clone());
+
+    for include in includes {
+        let name = include
+            .as_str()
+            .ok_or_else(|| format!("{file}: include expects file names, got {include}"))?;
+
+        let included = path.parent().unwrap_or(Path::new("")).join(name);
+        load_into(plan, &included, including)?;
+    }
+
+    including.pop();
+    plan.loaded.push(canonical);
+
+    // an included file's origin places its own blocks only
+    if !including.is_empty() {
+        push_down_origin(&file, &mut table)?;
+    }
+
+    plan.merge(&file, path.parent().unwrap_or(Path::new("")), table);
+    Ok(())
+}
+
+/// Moves the origin of an included file onto each of its blocks that has none.
+/// Transforms would have to pick a pivot among files, so they stay with the
+/// plan being run.
+fn push_down_origin(file: &str, table: &mut Map<String, Value>) -> Result<(), String> {
+    if table.contains_key("transform") || table.contains_key("reverse") {
+        return Err(format!(
+            "{file}: transform and reverse only apply in the plan being run, or on blocks"
+        ));
+    }
+
+    let Some(origin) = table.remove("origin") else {
+        return Ok(());
+    };
+
+    if let Some(Value::Array(blocks)) = table.get_mut("instruction") {
+        for block in blocks {
+            if let Value::Table(block) = block {
+                block.entry("origin").or_insert_with(|| origin.clone());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Path relative to the working directory when under it, for shorter messages
+fn display(path: &Path) -> String {
+    env::current_dir()
+        .ok()
+        .and_then(|current| path.strip_prefix(current).ok())
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
@@ -1,78 +1,386 @@
+pub mod control;
+mod expand;
+mod include;
+pub mod transform;
 mod utils;
+pub mod validate;
+
+use std::sync::Arc;
 
 use toml::{map::Map, Value};
+use tracing::{info_span, Span};
+
+use crate::script::Scripts;
 
-use self::utils::{get_action, get_destination, get_hand_slot, get_looking};
+use self::{
+    control::Control,
+    expand::{expand_path, variables, Library, Variables},
+    include::Plan,
+    transform::Transform,
+    utils::{
+        get_action, get_control, get_destination, get_hand_slot, get_looking, get_name, get_origin,
+        get_script,
+    },
+};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Looking {
     Front,
     Back,
     Direction(f64),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Action {
     Fly,
     Walk,
     RightClick,
+    /// Walks holding the left button down, breaking what is in the way
+    Mine,
+}
+
+impl Action {
+    /// Name used for this action in plan files
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Fly => "fly",
+            Action::Walk => "walk",
+            Action::RightClick => "right_clicking",
+            Action::Mine => "mining",
+        }
+    }
+
+    /// Action named as in plan files
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "fly" => Some(Action::Fly),
+            "walk" => Some(Action::Walk),
+            "right_clicking" => Some(Action::RightClick),
+            "mining" => Some(Action::Mine),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Instruction {
+    /// Position of the instruction within its plan
+    pub step: usize,
+    /// Index of the `[[instruction]]` block it comes from
+    pub block: usize,
+    pub name: String,
     pub destination: [f64; 3],
     pub hand_slot: char,
     pub reset_hand_stack: bool,
     pub looking: Looking,
     pub action: Action,
+    /// Script function that drives the step instead of the destination
+    pub script: Option<ScriptCall>,
+    /// Wait, hold, wait_until or if, which stay where the step before ends
+    pub control: Option<Control>,
 }
 
+/// A step handed over to a function of the plan's scripts
+#[derive(Debug, Clone)]
+pub struct ScriptCall {
+    pub scripts: Arc<Scripts>,
+    pub function: String,
+}
+
+/// Destination of a script or control step that gives none, until it is set to
+/// where the step before it ends
+const UNPLACED: [f64; 3] = [f64::NAN; 3];
+
+impl Instruction {
+    /// This step heading for a target its script picked
+    pub fn with_target(&self, action: Action, destination: [f64; 3], looking: Looking) -> Self {
+        Self {
+            action,
+            destination,
+            looking,
+            reset_hand_stack: matches!(action, Action::RightClick),
+            script: None,
+            control: None,
+            ..self.clone()
+        }
+    }
+
+    /// Name of what the step does, its control step or else its action
+    pub fn kind(&self) -> &'static str {
+        match &self.control {
+            Some(control) => control.name(),
+            None => self.action.name(),
+        }
+    }
+
+    /// Span grouping everything logged while this instruction runs
+    pub fn span(&self) -> Span {
+        info_span!(
+            "instruction",
+            step = self.step,
+            name = %self.name,
+            action = self.kind()
+        )
+    }
+}
+
+/// Instructions of the plan built into the binary
 pub fn list_instructions() -> Vec<Instruction> {
-    let toml: Value = toml::from_str(&include_str!("../../../instructions.toml")).unwrap();
+    let source = include_str!("../../../instructions.toml");
+    plan_instructions(&Plan::parse("instructions.toml", source).unwrap()).unwrap()
+}
+
+/// Instructions of a plan file read at runtime, along with the files it
+/// includes, refused when `plan check` would report an error
+pub fn load_instructions(path: &str) -> Result<Vec<Instruction>, String> {
+    let plan = include::load(path)?;
+    let report = validate::check_names(&plan);
+
+    if !report.errors.is_empty() {
+        return Err(report.errors.join("; "));
+    }
+
+    plan_instructions(&plan)
+}
+
+/// Errors name the file the failing block, waypoint or segment was written in
+fn plan_instructions(plan: &Plan) -> Result<Vec<Instruction>, String> {
+    let in_plan = |error: String| format!("{}: {error}", plan.file);
+
+    let table = &plan.table;
+    let plan_origin = get_origin(table).map_err(in_plan)?;
+    let plan_variables = variables(table, &Variables::new()).map_err(in_plan)?;
+    let library = Library::new(plan, &plan_variables)?;
+
+    let scripts = match plan.scripts.is_empty() {
+        true => None,
+        false => Some(Arc::new(Scripts::load(&plan.scripts).map_err(in_plan)?)),
+    };
+
+    let blocks = table
+        .get("instruction")
+        .and_then(Value::as_array)
+        .ok_or_else(|| in_plan("no [[instruction]] blocks".to_owned()))?;
+
+    let mut instructions = Vec::new();
+
+    for (index, (value, file)) in blocks.iter().zip(&plan.block_files).enumerate() {
+        let table = value
+            .as_table()
+            .ok_or_else(|| format!("{file}: [[instruction]] expects a table"))?;
+        let name = get_name(table);
+        let in_block = |error: String| format!("{file}: {name}: {error}");
+        let hand_slot = get_hand_slot(table).map_err(in_block)?;
+
+        let origin = get_origin(table).map_err(in_block)?.or(plan_origin);
+        let variables = variables(table, &plan_variables).map_err(in_block)?;
+
+        let mut block = path_to_instructions(
+            table,
+            &variables,
+            &library,
+            scripts.as_ref(),
+            name,
+            hand_slot,
+        )
+        .map_err(in_block)?;
+
+        for instruction in &mut block {
+            instruction.block = index;
+        }
+
+        if let Some(origin) = origin {
+            Transform::Translate(origin)
+                .apply(&mut block, origin)
+                .map_err(in_block)?;
+        }
+
+        apply_transforms(table, &mut block, origin).map_err(in_block)?;
+        instructions.extend(block);
+    }
+
+    apply_transforms(table, &mut instructions, plan_origin).map_err(in_plan)?;
 
-    toml.get("instruction")
-        .unwrap()
-        .as_array()
-        .unwrap()
+    let mut previous = None;
+
+    for (step, instruction) in instructions.iter_mut().enumerate() {
+        instruction.step = step;
+
+        if instruction.destination[0].is_nan() {
+            instruction.destination = previous.ok_or_else(|| {
+                in_plan(format!(
+                    "{}: the first step needs a destination",
+                    instruction.name
+                ))
+            })?;
+        }
+
+        previous = Some(instruction.destination);
+    }
+
+    Ok(instructions)
+}
+
+/// Applies `reverse = true` and the `transform` list of a table, turning
+/// around the origin or else the first destination given, script and control
+/// steps having none yet
+fn apply_transforms(
+    table: &Map<String, Value>,
+    instructions: &mut [Instruction],
+    origin: Option<[f64; 3]>,
+) -> Result<(), String> {
+    let first_placed = instructions
         .iter()
-        .flat_map(|value| {
-            let table = value.as_table().unwrap();
-            let hand_slot = get_hand_slot(table);
-            path_to_instructions(table, hand_slot)
-        })
-        .collect()
+        .map(|instruction| instruction.destination)
+        .find(|destination| !destination[0].is_nan());
+
+    let Some(pivot) = origin.or(first_placed) else {
+        return Ok(());
+    };
+
+    if table.get("reverse").and_then(Value::as_bool) == Some(true) {
+        Transform::Reverse.apply(instructions, pivot)?;
+    }
+
+    let transforms = table
+        .get("transform")
+        .and_then(Value::as_array)
+        .map_or(&[][..], Vec::as_slice);
+
+    for value in transforms {
+        if let Some(transform) = Transform::parse(value)? {
+            transform.apply(instructions, pivot)?;
+        }
+    }
+
+    Ok(())
 }
 
-fn path_to_instructions(value: &Map<String, Value>, hand_slot: char) -> Vec<Instruction> {
-    value
+/// Instructions of a block, its repeats and segments unrolled and its
+/// expressions and waypoints resolved
+fn path_to_instructions(
+    value: &Map<String, Value>,
+    variables: &Variables,
+    library: &Library,
+    scripts: Option<&Arc<Scripts>>,
+    name: &str,
+    hand_slot: char,
+) -> Result<Vec<Instruction>, String> {
+    let path = value
         .get("path")
-        .unwrap()
-        .as_array()
+        .and_then(Value::as_array)
+        .map_or(&[][..], Vec::as_slice);
+
+    expand_path(path, variables, library, &[])?
         .iter()
-        .flat_map(|value| {
-            value.iter().map(|value| {
-                let path_item = value.as_table().unwrap();
-                path_item_to_instruction(path_item, hand_slot)
-            })
-        })
+        .map(|path_item| path_item_to_instruction(path_item, scripts, name, hand_slot))
         .collect()
 }
 
-fn path_item_to_instruction(value: &Map<String, Value>, hand_slot: char) -> Instruction {
-    let action = get_action(value);
-    let destination = get_destination(value);
-    let looking = get_looking(value);
+fn path_item_to_instruction(
+    value: &Map<String, Value>,
+    scripts: Option<&Arc<Scripts>>,
+    name: &str,
+    hand_slot: char,
+) -> Result<Instruction, String> {
+    let script = get_script(value, scripts)?;
+    let control = get_control(value)?;
+
+    if let Some(control) = &control {
+        if value.contains_key("action") || value.contains_key("destination") {
+            return Err(format!(
+                "{} steps stay where the step before ends, without action or destination",
+                control.name()
+            ));
+        }
+    }
+
+    // a script step moves as its function says, so it may leave these out
+    let action = match (script.is_some() || control.is_some()) && !value.contains_key("action") {
+        true => Action::Walk,
+        false => get_action(value)?,
+    };
+
+    let destination = match get_destination(value)? {
+        Some(destination) => destination,
+        None if script.is_some() || control.is_some() => UNPLACED,
+        None => return Err("missing destination".to_owned()),
+    };
+
+    let looking = get_looking(value)?;
 
     let reset_hand_stack = match action {
         Action::RightClick => true,
         _ => false,
     };
 
-    Instruction {
+    Ok(Instruction {
+        step: 0,
+        block: 0,
+        name: name.to_owned(),
         action,
         destination,
         looking,
         reset_hand_stack,
         hand_slot,
+        script,
+        control,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(reverse: &str) -> String {
+        format!(
+            r#"
+            [[instruction]]
+            name = "Branch"
+            {reverse}
+            path = [
+                {{ action = "walk", destination = [0, 64, 0] }},
+                {{ if = "y < 60", then = [{{ action = "walk", destination = [1, 64, 0] }}] }},
+                {{ action = "walk", destination = [2, 64, 0] }},
+            ]
+            "#
+        )
+    }
+
+    #[test]
+    fn refuses_to_reverse_an_if() {
+        let reverses = [
+            "reverse = true",
+            r#"transform = ["reverse"]"#,
+            "transform = [{ reverse = true }]",
+        ];
+
+        for reverse in reverses {
+            let plan = Plan::parse("test.toml", &plan(reverse)).unwrap();
+
+            assert_eq!(
+                plan_instructions(&plan).map(|instructions| instructions.len()),
+                Err(
+                    "test.toml: Branch: reverse cannot turn steps that stay in place around"
+                        .to_owned()
+                ),
+                "{reverse}"
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_an_if_that_is_not_reversed() {
+        let plan = Plan::parse("test.toml", &plan("")).unwrap();
+        let instructions = plan_instructions(&plan).unwrap();
+
+        assert!(matches!(
+            instructions[1].control,
+            Some(Control::If {
+                then_steps: 1,
+                else_steps: 0,
+                ..
+            })
+        ));
     }
 }
@@ -0,0 +1,161 @@
+use toml::Value;
+
+use super::{utils::as_number, Instruction, Looking};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Axis {
+    X,
+    Z,
+}
+
+/// A change applied to every destination of a plan, or of one instruction block
+#[derive(Debug, Clone, Copy)]
+pub enum Transform {
+    Translate([f64; 3]),
+    /// Quarter turns clockwise seen from above, around the pivot
+    Rotate(i32),
+    /// Flips one horizontal axis around the pivot
+    Mirror(Axis),
+    /// Runs the path backwards, each leg keeping its action
+    Reverse,
+}
+
+impl Transform {
+    /// Transform from a plan file: `"reverse"`, `{ translate = [x, y, z] }`,
+    /// `{ rotate = 90 }`, `{ mirror = "x" }` or `{ reverse = true }`
+    pub fn parse(value: &Value) -> Result<Option<Self>, String> {
+        if value.as_str() == Some("reverse") {
+            return Ok(Some(Transform::Reverse));
+        }
+
+        let table = value
+            .as_table()
+            .filter(|table| table.len() == 1)
+            .ok_or_else(|| format!("transform {value} should have a single key"))?;
+
+        let (name, argument) = table.iter().next().unwrap();
+
+        match name.as_str() {
+            "translate" => {
+                let offset = argument
+                    .as_array()
+                    .map(|coords| coords.iter().filter_map(as_number).collect::<Vec<f64>>())
+                    .and_then(|coords| <[f64; 3]>::try_from(coords).ok())
+                    .ok_or_else(|| format!("translate expects [x, y, z], got {argument}"))?;
+
+                Ok(Some(Transform::Translate(offset)))
+            }
+
+            "rotate" => {
+                let degrees = as_number(argument)
+                    .ok_or_else(|| format!("rotate expects degrees, got {argument}"))?;
+
+                Self::rotate(degrees).map(Some)
+            }
+
+            "mirror" => {
+                let axis = argument.as_str().unwrap_or_default();
+                Self::mirror(axis).map(Some)
+            }
+
+            "reverse" => Ok(argument
+                .as_bool()
+                .unwrap_or(false)
+                .then_some(Transform::Reverse)),
+
+            _ => Err(format!("unknown transform {name}")),
+        }
+    }
+
+    pub fn rotate(degrees: f64) -> Result<Self, String> {
+        match degrees % 90.0 == 0.0 {
+            true => Ok(Transform::Rotate((degrees / 90.0) as i32)),
+            false => Err(format!("rotate only takes steps of 90°, got {degrees}")),
+        }
+    }
+
+    pub fn mirror(axis: &str) -> Result<Self, String> {
+        match axis {
+            "x" => Ok(Transform::Mirror(Axis::X)),
+            "z" => Ok(Transform::Mirror(Axis::Z)),
+            _ => Err(format!("mirror expects \"x\" or \"z\", got {axis:?}")),
+        }
+    }
+
+    /// Fails to reverse control steps, whose `if` branches would end up
+    /// before the condition choosing them
+    pub fn apply(&self, instructions: &mut [Instruction], pivot: [f64; 3]) -> Result<(), String> {
+        match *self {
+            Transform::Translate(offset) => {
+                for instruction in instructions.iter_mut() {
+                    for (coordinate, offset) in instruction.destination.iter_mut().zip(offset) {
+                        *coordinate += offset;
+                    }
+                }
+            }
+
+            Transform::Rotate(quarters) => {
+                for instruction in instructions.iter_mut() {
+                    let [x, y, z] = instruction.destination;
+                    let (mut dx, mut dz) = (x - pivot[0], z - pivot[2]);
+
+                    // north becomes east, east becomes south
+                    for _ in 0..quarters.rem_euclid(4) {
+                        (dx, dz) = (-dz, dx);
+                    }
+
+                    instruction.destination = [pivot[0] + dx, y, pivot[2] + dz];
+                    turn(&mut instruction.looking, |yaw| yaw + 90.0 * quarters as f64);
+                }
+            }
+
+            Transform::Mirror(axis) => {
+                for instruction in instructions.iter_mut() {
+                    match axis {
+                        Axis::X => {
+                            instruction.destination[0] =
+                                2.0 * pivot[0] - instruction.destination[0];
+                            turn(&mut instruction.looking, |yaw| -yaw);
+                        }
+                        Axis::Z => {
+                            instruction.destination[2] =
+                                2.0 * pivot[2] - instruction.destination[2];
+                            turn(&mut instruction.looking, |yaw| 180.0 - yaw);
+                        }
+                    }
+                }
+            }
+
+            Transform::Reverse => {
+                if instructions.iter().any(|step| step.control.is_some()) {
+                    return Err("reverse cannot turn steps that stay in place around".to_owned());
+                }
+
+                // a leg is described by the step it leads to, so after reversing
+                // every destination takes the description of the step after it
+                let destinations: Vec<[f64; 3]> = instructions
+                    .iter()
+                    .rev()
+                    .map(|instruction| instruction.destination)
+                    .collect();
+
+                if !instructions.is_empty() {
+                    instructions[1..].reverse();
+                }
+
+                for (instruction, destination) in instructions.iter_mut().zip(destinations) {
+                    instruction.destination = destination;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Applies a change of yaw to a fixed looking direction, kept within -180..180
+fn turn(looking: &mut Looking, change: impl Fn(f64) -> f64) {
+    if let Looking::Direction(yaw) = looking {
+        *yaw = (change(*yaw) + 180.0).rem_euclid(360.0) - 180.0;
+    }
+}
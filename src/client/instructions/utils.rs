@@ -1,61 +1,216 @@
+use std::{sync::Arc, time::Duration};
+
 use toml::{map::Map, Value};
 
-use super::{Action, Looking};
+use crate::script::Scripts;
 
-pub fn get_action(table: &Map<String, Value>) -> Action {
-    let action = table.get("action").unwrap().as_str().unwrap();
+use super::{
+    control::{milliseconds, Condition, Control, DEFAULT_TIMEOUT, INPUTS},
+    expand::{Variables, ELSE_STEPS, THEN_STEPS},
+    Action, Looking, ScriptCall,
+};
 
-    match action {
-        "walk" => Action::Walk,
-        "fly" => Action::Fly,
-        _ => Action::RightClick,
-    }
+pub fn get_action(table: &Map<String, Value>) -> Result<Action, String> {
+    let action = table.get("action").ok_or("missing action")?;
+
+    action
+        .as_str()
+        .and_then(Action::parse)
+        .ok_or_else(|| format!("unknown action {action}"))
 }
 
-pub fn get_destination(table: &Map<String, Value>) -> [f64; 3] {
-    table
-        .get("destination")
-        .and_then(|coords| {
-            let coords = coords
-                .as_array()
-                .unwrap()
-                .iter()
-                .map(|coord| coord.as_float().unwrap())
-                .collect::<Vec<f64>>();
+pub fn get_destination(table: &Map<String, Value>) -> Result<Option<[f64; 3]>, String> {
+    let Some(destination) = table.get("destination") else {
+        return Ok(None);
+    };
+
+    destination
+        .as_array()
+        .and_then(|coords| coords.iter().map(as_number).collect::<Option<Vec<f64>>>())
+        .and_then(|coords| <[f64; 3]>::try_from(coords).ok())
+        .map(Some)
+        .ok_or_else(|| format!("destination expects [x, y, z], got {destination}"))
+}
 
-            assert_eq!(coords.len(), 3);
+/// Script function a step hands over to, checked against the plan's scripts
+pub fn get_script(
+    table: &Map<String, Value>,
+    scripts: Option<&Arc<Scripts>>,
+) -> Result<Option<ScriptCall>, String> {
+    let Some(function) = table.get("script") else {
+        return Ok(None);
+    };
 
-            Some([coords[0], coords[1], coords[2]])
-        })
-        .unwrap()
+    let function = function
+        .as_str()
+        .ok_or_else(|| format!("script expects a function name, got {function}"))?;
+
+    let scripts =
+        scripts.ok_or_else(|| format!("script {function} but the plan has no scripts"))?;
+
+    if !scripts.has_step(function) {
+        return Err(format!("no script function {function}(player)"));
+    }
+
+    Ok(Some(ScriptCall {
+        scripts: scripts.clone(),
+        function: function.to_owned(),
+    }))
 }
 
-pub fn get_looking(table: &Map<String, Value>) -> Looking {
-    let looking = table.get("looking");
+/// The wait, hold, wait_until or if of a path entry, its numbers and
+/// conditions already evaluated
+pub fn get_control(table: &Map<String, Value>) -> Result<Option<Control>, String> {
+    let duration = |key: &str| -> Result<Duration, String> {
+        let value = table.get(key).ok_or_else(|| format!("missing {key}"))?;
+
+        as_number(value)
+            .ok_or_else(|| format!("{key} expects milliseconds, got {value}"))
+            .and_then(|value| milliseconds(value, key))
+    };
+
+    let condition = |key: &str| -> Result<Condition, String> {
+        let value = &table[key];
+        let text = value
+            .as_str()
+            .ok_or_else(|| format!("{key} expects a condition, got {value}"))?;
+
+        Condition::parse(text, &Variables::new())
+    };
+
+    if table.contains_key("wait") {
+        return Ok(Some(Control::Wait(duration("wait")?)));
+    }
+
+    if let Some(input) = table.get("hold") {
+        let input = INPUTS
+            .into_iter()
+            .find(|name| input.as_str() == Some(name))
+            .ok_or_else(|| format!("hold expects one of {}, got {input}", INPUTS.join(", ")))?;
+
+        return Ok(Some(Control::Hold {
+            input,
+            duration: duration("ms")?,
+        }));
+    }
+
+    if table.contains_key("wait_until") {
+        return Ok(Some(Control::WaitUntil {
+            condition: condition("wait_until")?,
+            timeout: match table.contains_key("timeout") {
+                true => duration("timeout")?,
+                false => DEFAULT_TIMEOUT,
+            },
+        }));
+    }
+
+    if table.contains_key("if") {
+        let steps = |key: &str| {
+            table
+                .get(key)
+                .and_then(Value::as_integer)
+                .map_or(0, |steps| steps as usize)
+        };
+
+        return Ok(Some(Control::If {
+            condition: condition("if")?,
+            then_steps: steps(THEN_STEPS),
+            else_steps: steps(ELSE_STEPS),
+        }));
+    }
+
+    if let Some(button) = table.get("click") {
+        return match button.as_str() {
+            Some("left") => Ok(Some(Control::Click("left_click"))),
+            Some("right") => Ok(Some(Control::Click("right_click"))),
+            _ => Err(format!("click expects \"left\" or \"right\", got {button}")),
+        };
+    }
 
-    if looking.is_none() {
-        return Looking::Front;
+    if let Some(amount) = table.get("drop") {
+        return match amount.as_str() {
+            Some("item") => Ok(Some(Control::Drop { stack: false })),
+            Some("stack") => Ok(Some(Control::Drop { stack: true })),
+            _ => Err(format!("drop expects \"item\" or \"stack\", got {amount}")),
+        };
     }
 
-    let looking = looking.unwrap();
+    if table.contains_key("swap_hands") {
+        return Ok(Some(Control::SwapHands));
+    }
 
-    if let Some(direction) = looking.as_float() {
-        return Looking::Direction(direction);
+    if let Some(slot) = table.get("select") {
+        return match slot.as_integer() {
+            Some(slot @ 1..=9) => Ok(Some(Control::Select(slot as u8))),
+            _ => Err(format!(
+                "select expects a hotbar slot from 1 to 9, got {slot}"
+            )),
+        };
     }
 
-    if let Some(direction) = looking.as_str() {
-        return match direction {
-            "back" => Looking::Back,
-            _ => Looking::Front,
+    if let Some(text) = table.get("chat") {
+        return match text.as_str() {
+            Some(text) if !text.is_empty() => Ok(Some(Control::Chat(text.to_owned()))),
+            _ => Err(format!("chat expects the text to send, got {text}")),
         };
     }
 
-    panic!("direction could not be parsed");
+    Ok(None)
+}
+
+/// Origin relative destinations are measured from, if the table declares one
+pub fn get_origin(table: &Map<String, Value>) -> Result<Option<[f64; 3]>, String> {
+    let Some(origin) = table.get("origin") else {
+        return Ok(None);
+    };
+
+    origin
+        .as_array()
+        .map(|coords| coords.iter().filter_map(as_number).collect::<Vec<f64>>())
+        .and_then(|coords| <[f64; 3]>::try_from(coords).ok())
+        .map(Some)
+        .ok_or_else(|| format!("origin expects [x, y, z], got {origin}"))
+}
+
+/// Floats and integers alike, so relative coordinates can be written as `[0, 0, -4]`
+pub fn as_number(value: &Value) -> Option<f64> {
+    value
+        .as_float()
+        .or_else(|| value.as_integer().map(|integer| integer as f64))
 }
 
-pub fn get_hand_slot(table: &Map<String, Value>) -> char {
+pub fn get_looking(table: &Map<String, Value>) -> Result<Looking, String> {
+    let Some(looking) = table.get("looking") else {
+        return Ok(Looking::Front);
+    };
+
+    if let Some(direction) = as_number(looking) {
+        return Ok(Looking::Direction(direction));
+    }
+
+    match looking.as_str() {
+        Some("front") => Ok(Looking::Front),
+        Some("back") => Ok(Looking::Back),
+        _ => Err(format!(
+            "looking expects \"front\", \"back\" or a yaw, got {looking}"
+        )),
+    }
+}
+
+pub fn get_hand_slot(table: &Map<String, Value>) -> Result<char, String> {
+    let Some(slot) = table.get("hand_slot") else {
+        return Ok('1');
+    };
+
+    match slot.as_str().map(|slot| slot.as_bytes()) {
+        Some([slot @ b'1'..=b'9']) => Ok(char::from(*slot)),
+        _ => Err(format!("hand_slot expects \"1\" to \"9\", got {slot}")),
+    }
+}
+
+pub fn get_name(table: &Map<String, Value>) -> &str {
     table
-        .get("hand_slot")
-        .and_then(|item| item.as_str().unwrap().chars().next())
-        .unwrap_or('1')
-}
\ No newline at end of file
+        .get("name")
+        .and_then(|name| name.as_str())
+        .unwrap_or("Unknown")
+}
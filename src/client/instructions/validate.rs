@@ -0,0 +1,237 @@
+use std::collections::BTreeSet;
+
+use toml::{map::Map, Value};
+
+use super::{
+    expand::{number, Variables},
+    include::{self, Plan},
+    plan_instructions, Action,
+};
+
+/// Keys of the steps that stay where the step before them ends
+const CONTROLS: [&str; 8] = [
+    "wait",
+    "hold",
+    "wait_until",
+    "click",
+    "drop",
+    "swap_hands",
+    "select",
+    "chat",
+];
+
+/// Problems found in a plan file without running it
+#[derive(Default)]
+pub struct Report {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+    /// Steps the plan expands to, when it loads
+    pub steps: Option<usize>,
+}
+
+/// Names a plan declares and the ones its paths use
+#[derive(Default)]
+struct Names {
+    waypoints: BTreeSet<String>,
+    segments: BTreeSet<String>,
+    used_waypoints: BTreeSet<String>,
+    used_segments: BTreeSet<String>,
+}
+
+fn declared(plan: &Map<String, Value>, key: &str) -> BTreeSet<String> {
+    plan.get(key)
+        .and_then(Value::as_table)
+        .map(|table| table.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Checks every path entry of a plan and the files it includes, even the ones
+/// only reachable through a segment or a repeat that is never used, then loads
+/// the plan to catch the rest
+pub fn validate(path: &str) -> Report {
+    let loaded = match include::load(path) {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            return Report {
+                errors: vec![error],
+                ..Report::default()
+            }
+        }
+    };
+
+    let mut report = check_names(&loaded);
+
+    // names are fine, so whatever fails now is a value that does not evaluate
+    if report.errors.is_empty() {
+        match plan_instructions(&loaded) {
+            Ok(instructions) => report.steps = Some(instructions.len()),
+            Err(error) => report.errors.push(error),
+        }
+    }
+
+    report
+}
+
+/// The checks of `validate` that come before loading the plan, for a plan
+/// already read along with its includes
+pub(super) fn check_names(loaded: &Plan) -> Report {
+    let mut report = Report::default();
+    let plan = &loaded.table;
+
+    let mut names = Names {
+        waypoints: declared(plan, "waypoints"),
+        segments: declared(plan, "segments"),
+        ..Names::default()
+    };
+
+    match plan.get("instruction").and_then(Value::as_array) {
+        Some(blocks) => {
+            for (index, (block, file)) in blocks.iter().zip(&loaded.block_files).enumerate() {
+                let name = block
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .map_or_else(|| format!("block {}", index + 1), str::to_owned);
+                let name = format!("{file}: {name}");
+
+                match block.get("path").and_then(Value::as_array) {
+                    Some(path) => check_path(path, &name, "", &mut names, &mut report),
+                    None => report.errors.push(format!("{name}: missing path")),
+                }
+            }
+        }
+        None => report
+            .errors
+            .push(format!("{}: no [[instruction]] blocks", loaded.file)),
+    }
+
+    if let Some(segments) = plan.get("segments").and_then(Value::as_table) {
+        for (name, path) in segments {
+            let label = format!("{}: segment {name}", loaded.file_of("segments", name));
+
+            match path.as_array() {
+                Some(path) => check_path(path, &label, "", &mut names, &mut report),
+                None => report.errors.push(format!("{label}: expected a path")),
+            }
+        }
+    }
+
+    for name in names.waypoints.difference(&names.used_waypoints) {
+        let file = loaded.file_of("waypoints", name);
+        report
+            .warnings
+            .push(format!("{file}: waypoint {name} is never used"));
+    }
+
+    for name in names.segments.difference(&names.used_segments) {
+        let file = loaded.file_of("segments", name);
+        report
+            .warnings
+            .push(format!("{file}: segment {name} is never used"));
+    }
+
+    report
+}
+
+/// `entry` numbers the repeat being checked, so its entries read as `3.1`, `3.2`
+fn check_path(path: &[Value], owner: &str, entry: &str, names: &mut Names, report: &mut Report) {
+    for (index, value) in path.iter().enumerate() {
+        let entry = format!("{entry}{}", index + 1);
+        let label = format!("{owner}, entry {entry}");
+
+        let Some(item) = value.as_table() else {
+            report.errors.push(format!("{label}: expected a table"));
+            continue;
+        };
+
+        if item.contains_key("repeat") {
+            match item.get("path").and_then(Value::as_array) {
+                Some(path) => check_path(path, owner, &format!("{entry}."), names, report),
+                None => report.errors.push(format!("{label}: repeat needs a path")),
+            }
+
+            continue;
+        }
+
+        if item.contains_key("if") {
+            for branch in ["then", "else"] {
+                match item.get(branch) {
+                    Some(Value::Array(path)) => {
+                        let entry = format!("{entry}.{branch}.");
+                        check_path(path, owner, &entry, names, report);
+                    }
+                    Some(_) => report
+                        .errors
+                        .push(format!("{label}: {branch} expects a path")),
+                    None => {}
+                }
+            }
+
+            continue;
+        }
+
+        // the rest of a control step is checked when the plan loads
+        if CONTROLS.iter().any(|key| item.contains_key(*key)) {
+            continue;
+        }
+
+        if let Some(segment) = item.get("segment") {
+            match segment.as_str() {
+                Some(name) if names.segments.contains(name) => {
+                    names.used_segments.insert(name.to_owned());
+                }
+                Some(name) => report
+                    .errors
+                    .push(format!("{label}: unknown segment {name}")),
+                None => report
+                    .errors
+                    .push(format!("{label}: segment expects a name")),
+            }
+
+            continue;
+        }
+
+        // a script step moves as its function says, so it may leave out
+        // the action and the destination
+        let is_script = match item.get("script") {
+            Some(Value::String(_)) => true,
+            Some(_) => {
+                report
+                    .errors
+                    .push(format!("{label}: script expects a function name"));
+                true
+            }
+            None => false,
+        };
+
+        match item.get("action").and_then(Value::as_str) {
+            Some(action) if Action::parse(action).is_some() => {}
+            Some(action) => report
+                .errors
+                .push(format!("{label}: unknown action {action}")),
+            None if is_script => {}
+            None => report.errors.push(format!("{label}: missing action")),
+        }
+
+        match item.get("destination") {
+            Some(Value::String(name)) if names.waypoints.contains(name) => {
+                names.used_waypoints.insert(name.to_owned());
+            }
+            Some(Value::String(name)) => report
+                .errors
+                .push(format!("{label}: unknown waypoint {name}")),
+            // expressions are evaluated with the variables in scope once the plan loads
+            Some(Value::Array(coords)) if coords.len() == 3 => {
+                for coord in coords.iter().filter(|coord| !coord.is_str()) {
+                    if let Err(error) = number(coord, &Variables::new()) {
+                        report.errors.push(format!("{label}: destination {error}"));
+                    }
+                }
+            }
+            Some(destination) => report
+                .errors
+                .push(format!("{label}: expected [x, y, z], got {destination}")),
+            None if is_script => {}
+            None => report.errors.push(format!("{label}: missing destination")),
+        }
+    }
+}
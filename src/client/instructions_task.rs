@@ -1,29 +1,599 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
 use crate::{
+    ace_state::AceState,
+    commands::{Ack, Command, CommandFrame, Parameters},
     handlers::message::Message,
-    instructions::{list_instructions, Looking},
+    instructions::{
+        control::Control, list_instructions, load_instructions, Action, Instruction, Looking,
+        ScriptCall,
+    },
+    metrics::ClientMetrics,
     minecraft_resource::MinecraftResource,
+    plan::materials,
+    report::{self, IncidentKind, Report, RunOutcome, StepOutcome},
+    script::Directive,
+    status::{Plan, RunState, Status},
     vectors::{CalculateAngleForce, Vector3D},
-    DISTANCE_TO_FINISH_INSTRUCTION, TICKRATE_DURATION,
+    TICKRATE_DURATION,
 };
 
 use futures_util::lock::Mutex;
-use tokio::{sync::broadcast::Sender, time::sleep};
+use rhai::{Dynamic, Map};
+use tokio::{
+    sync::{
+        broadcast::Sender,
+        mpsc::{UnboundedReceiver, UnboundedSender},
+    },
+    time::sleep,
+};
+use tracing::{debug, info, info_span, warn, Span};
+
+/// State of the run that commands act upon, owned by the instructions task
+struct Run {
+    /// Remaining instructions, the current one being the last
+    instructions: Vec<Instruction>,
+    is_paused: bool,
+    parameters: Parameters,
+    /// Length of the plan when it was started
+    step_count: usize,
+    /// Where the player was on the previous tick, to measure its speed
+    last_position: Option<(Vector3D, Instant)>,
+    /// Smoothed speed in blocks per second
+    speed: Option<f64>,
+    error: Option<String>,
+    /// Span of the whole run, parent of the instruction spans
+    span: Span,
+    /// Span of the current instruction, keyed by its step
+    instruction_span: Option<(usize, Span)>,
+    /// Memory and ticks so far of the script step running, keyed by its step
+    script_state: Option<(usize, Dynamic, u64)>,
+    /// When the control step running started, keyed by its step
+    control_started: Option<(usize, Instant)>,
+    /// Slot the last select step that ran picked, keyed by its block
+    selected_slot: Option<(usize, char)>,
+}
+
+impl Run {
+    fn start(mut instructions: Vec<Instruction>) -> Self {
+        if let Err(error) = materials::check_inventory(&instructions) {
+            warn!(%error, "could not check the inventory");
+        }
 
-pub fn spawn(minecraft_resource: Arc<Mutex<MinecraftResource>>, tx: Sender<Message>) -> () {
-    tokio::spawn(async move {
-        let mut instructions = list_instructions();
         instructions.reverse();
 
-        while let Some(instruction) = instructions.last().cloned() {
+        Self {
+            step_count: instructions.len(),
+            span: info_span!("run", plan_length = instructions.len()),
+            instructions,
+            is_paused: false,
+            parameters: Parameters::new(),
+            last_position: None,
+            speed: None,
+            error: None,
+            instruction_span: None,
+            script_state: None,
+            control_started: None,
+            selected_slot: None,
+        }
+    }
+
+    fn instruction_span(&mut self, instruction: &Instruction) -> Span {
+        match &self.instruction_span {
+            Some((step, span)) if *step == instruction.step => span.clone(),
+
+            _ => {
+                let span = self.span.in_scope(|| instruction.span());
+                span.in_scope(
+                    || info!(destination = ?instruction.destination, "instruction started"),
+                );
+
+                self.instruction_span = Some((instruction.step, span.clone()));
+                span
+            }
+        }
+    }
+
+    fn track_speed(&mut self, position: &Vector3D) {
+        if let Some((last_position, at)) = &self.last_position {
+            let seconds = at.elapsed().as_secs_f64();
+
+            if seconds > 0.0 {
+                let speed = last_position.distance_to(position) / seconds;
+
+                self.speed = Some(match self.speed {
+                    Some(previous) => previous * 0.9 + speed * 0.1,
+                    None => speed,
+                });
+            }
+        }
+
+        self.last_position = Some((position.clone(), Instant::now()));
+    }
+
+    /// Asks the script of a step what to do on this tick, with the memory it
+    /// kept since the step started
+    fn run_script(
+        &mut self,
+        instruction: &Instruction,
+        call: &ScriptCall,
+        minecraft_resource: &MinecraftResource,
+    ) -> Result<Directive, String> {
+        if !matches!(self.script_state, Some((step, ..)) if step == instruction.step) {
+            self.script_state = Some((instruction.step, Dynamic::from_map(Map::new()), 0));
+        }
+
+        let (_, memory, ticks) = self.script_state.as_mut().unwrap();
+        let directive = call
+            .scripts
+            .call(&call.function, minecraft_resource, *ticks, memory);
+
+        *ticks += 1;
+        directive
+    }
+
+    /// Moves a step that stays in place along, telling whether it is over. An
+    /// if drops the branch that does not run, leaving the other one to come
+    /// next.
+    fn run_control(
+        &mut self,
+        instruction: &Instruction,
+        control: &Control,
+        minecraft_resource: &MinecraftResource,
+        tx: &Sender<Message>,
+    ) -> Result<bool, String> {
+        let started_at = match self.control_started {
+            Some((step, started_at)) if step == instruction.step => started_at,
+
+            _ => {
+                if let Control::Select(slot) = control {
+                    self.selected_slot = Some((instruction.block, char::from(b'0' + slot)));
+                }
+
+                if control.is_input() {
+                    tx.send(Message::Input {
+                        instruction: instruction.clone(),
+                        held_slot: minecraft_resource.held_slot(),
+                    })
+                    .unwrap();
+                }
+
+                let now = Instant::now();
+                self.control_started = Some((instruction.step, now));
+                now
+            }
+        };
+
+        let elapsed = started_at.elapsed();
+
+        if let Some(duration) = control.duration() {
+            return Ok(elapsed >= duration);
+        }
+
+        match control {
+            Control::WaitUntil { condition, timeout } => {
+                match condition.holds(minecraft_resource) {
+                    Ok(true) => Ok(true),
+                    _ if elapsed >= *timeout => Err(format!(
+                        "{condition} still false after {:.1}s",
+                        timeout.as_secs_f64()
+                    )),
+                    _ => Ok(false),
+                }
+            }
+
+            Control::If {
+                condition,
+                then_steps,
+                else_steps,
+            } => {
+                let holds = condition
+                    .holds(minecraft_resource)
+                    .map_err(|error| format!("if {condition}: {error}"))?;
+
+                // steps are stored last first, so the branches sit below the if
+                let end = self.instructions.len() - 1 - then_steps;
+                let skipped = match holds {
+                    true => end - else_steps..end,
+                    false => end..end + then_steps,
+                };
+
+                info!(%condition, holds, skipped = skipped.len(), "branch chosen");
+                self.instructions.drain(skipped);
+                Ok(true)
+            }
+
+            _ => Ok(true),
+        }
+    }
+
+    /// Stops the run where it is after a step failed, until it is resumed
+    fn pause_on_error(&mut self, error: &str, tx: &Sender<Message>) {
+        self.release(tx);
+        self.is_paused = true;
+        self.control_started = None;
+        self.error = Some(error.to_owned());
+    }
+
+    /// Remaining steps in the order they run, the whole plan right after a start
+    fn plan(&self) -> Plan {
+        Plan::new(self.instructions.iter().rev())
+    }
+
+    /// Copies the state of the run into the status published to the relay
+    fn publish(&self, status: &mut Status, position: Option<&Vector3D>) {
+        let current = self.instructions.last();
+
+        status.state = match (current, self.is_paused) {
+            (None, _) => RunState::Idle,
+            (Some(_), true) => RunState::Paused,
+            (Some(_), false) => RunState::Running,
+        };
+
+        status.step_count = self.step_count;
+        status.remaining_steps = self.instructions.len();
+        status.step_index = self.step_count.saturating_sub(self.instructions.len());
+        status.step_name = current.map(|instruction| instruction.name.clone());
+        status.action = current.map(Instruction::kind);
+        status.error = self.error.clone();
+
+        let distance_to_goal = current.zip(position).map(|(instruction, position)| {
+            position.distance_to(&Vector3D::from(instruction.destination))
+        });
+
+        let remaining_path: f64 = self
+            .instructions
+            .windows(2)
+            .map(|pair| {
+                Vector3D::from(pair[0].destination)
+                    .distance_to(&Vector3D::from(pair[1].destination))
+            })
+            .sum();
+
+        status.distance_to_goal = distance_to_goal;
+        status.eta_seconds = distance_to_goal
+            .zip(self.speed.filter(|speed| *speed > 0.01))
+            .map(|(distance, speed)| (distance + remaining_path) / speed);
+    }
+
+    /// The step with the slot a select before it in its block picked, the
+    /// block's own slot until one runs
+    fn with_hand_slot(&self, instruction: &Instruction) -> Instruction {
+        let mut instruction = instruction.clone();
+
+        if let Some((block, slot)) = self.selected_slot {
+            if block == instruction.block {
+                instruction.hand_slot = slot;
+            }
+        }
+
+        instruction
+    }
+
+    /// Lets go of whatever the current instruction is holding
+    fn release(&self, tx: &Sender<Message>) {
+        if let Some(instruction) = self.instructions.last() {
+            tx.send(Message::InstructionFinished(instruction.clone()))
+                .unwrap();
+        }
+    }
+
+    fn apply(&mut self, command: &Command, tx: &Sender<Message>) -> Result<(), String> {
+        match command {
+            Command::Start { plan } => {
+                let mut instructions = match plan {
+                    Some(path) => load_instructions(path)?,
+                    None => list_instructions(),
+                };
+
+                materials::check_inventory(&instructions)?;
+                instructions.reverse();
+                self.release(tx);
+                self.step_count = instructions.len();
+                self.span = info_span!("run", plan_length = instructions.len(), plan = ?plan);
+                self.instruction_span = None;
+                self.script_state = None;
+                self.control_started = None;
+                self.selected_slot = None;
+                self.instructions = instructions;
+                self.is_paused = false;
+                self.last_position = None;
+            }
+
+            Command::Pause => {
+                if self.instructions.is_empty() {
+                    return Err("no plan is running".to_owned());
+                }
+
+                self.release(tx);
+                self.is_paused = true;
+                self.control_started = None;
+                self.last_position = None;
+            }
+
+            Command::Resume => {
+                if !self.is_paused {
+                    return Err("the plan is not paused".to_owned());
+                }
+
+                self.is_paused = false;
+            }
+
+            Command::Abort => {
+                self.release(tx);
+                self.instructions.clear();
+                self.script_state = None;
+                self.control_started = None;
+                self.is_paused = false;
+            }
+
+            Command::Skip => {
+                self.release(tx);
+                self.script_state = None;
+                self.control_started = None;
+                let skipped = self
+                    .instructions
+                    .pop()
+                    .ok_or_else(|| "no instruction to skip".to_owned())?;
+
+                // skipping an if skips both of its branches
+                if let Some(Control::If {
+                    then_steps,
+                    else_steps,
+                    ..
+                }) = skipped.control
+                {
+                    let end = self.instructions.len() - then_steps - else_steps;
+                    self.instructions.drain(end..);
+                }
+            }
+
+            Command::Set { name, value } => self.parameters.set(name, *value)?,
+        }
+
+        Ok(())
+    }
+}
+
+/// What a hotbar key that never showed up in the telemetry left held
+fn hand_slot_error(slot: char, held: Option<char>) -> String {
+    match held {
+        Some(held) => format!("hand slot {slot} never selected, {held} still held"),
+        None => format!("hand slot {slot} never selected"),
+    }
+}
+
+/// Keeps the run report in step with a command that was applied
+async fn report_command(
+    command: &Command,
+    run: &Run,
+    report: &Mutex<Report>,
+    ace_state: &Mutex<AceState>,
+) {
+    let flight_pulses = ace_state.lock().await.flight_pulses;
+
+    match command {
+        Command::Start { plan } => {
+            report::close(report, RunOutcome::Replaced, flight_pulses).await;
+            report.lock().await.begin(plan.clone(), run.step_count);
+        }
+
+        Command::Abort => report::close(report, RunOutcome::Aborted, flight_pulses).await,
+
+        Command::Skip => report
+            .lock()
+            .await
+            .step_ended(StepOutcome::Skipped, flight_pulses),
+
+        Command::Resume => report.lock().await.resumed(),
+
+        _ => (),
+    }
+}
+
+/// Ends the current step, the next tick starting the one after it
+async fn finish_step(
+    run: &mut Run,
+    instruction: Instruction,
+    report: &Mutex<Report>,
+    ace_state: &Mutex<AceState>,
+    tx: &Sender<Message>,
+) {
+    let flight_pulses = ace_state.lock().await.flight_pulses;
+    report
+        .lock()
+        .await
+        .step_ended(StepOutcome::Reached, flight_pulses);
+
+    run.instructions.pop();
+
+    if run.instructions.is_empty() {
+        report::close(report, RunOutcome::Completed, flight_pulses).await;
+    }
+
+    let tx = tx.clone();
+
+    tokio::task::spawn(async move {
+        sleep(TICKRATE_DURATION * 10).await;
+
+        let message = Message::InstructionFinished(instruction);
+        tx.send(message).unwrap();
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn spawn(
+    minecraft_resource: Arc<Mutex<MinecraftResource>>,
+    ace_state: Arc<Mutex<AceState>>,
+    metrics: Arc<Mutex<ClientMetrics>>,
+    status: Arc<Mutex<Status>>,
+    report: Arc<Mutex<Report>>,
+    tx: Sender<Message>,
+    mut commands: UnboundedReceiver<CommandFrame>,
+    outgoing: UnboundedSender<String>,
+) -> () {
+    tokio::spawn(async move {
+        let mut run = Run::start(list_instructions());
+        outgoing.send(run.plan().to_json()).unwrap();
+        report.lock().await.begin(None, run.step_count);
+
+        loop {
             sleep(TICKRATE_DURATION).await;
 
+            while let Ok(frame) = commands.try_recv() {
+                let result = run.apply(&frame.command, &tx);
+
+                run.span.in_scope(|| match &result {
+                    Ok(()) => info!(id = %frame.id, command = frame.command.name(), "command applied"),
+                    Err(error) => {
+                        warn!(id = %frame.id, command = frame.command.name(), %error, "command failed")
+                    }
+                });
+
+                if result.is_ok() {
+                    report_command(&frame.command, &run, &report, &ace_state).await;
+                }
+
+                if result.is_ok() && matches!(frame.command, Command::Start { .. }) {
+                    outgoing.send(run.plan().to_json()).unwrap();
+                }
+
+                run.error = result.clone().err();
+                outgoing.send(Ack::new(&frame, result).to_json()).unwrap();
+            }
+
+            let instruction = match run.instructions.last() {
+                Some(instruction) if !run.is_paused => instruction.clone(),
+
+                _ => {
+                    run.publish(&mut *status.lock().await, None);
+                    continue;
+                }
+            };
+
+            let tick_started = Instant::now();
+            let span = run.instruction_span(&instruction);
+
             let minecraft_resource = minecraft_resource.lock().await;
 
+            let mut run_report = report.lock().await;
+
+            if run_report.is_between_steps() {
+                let flight_pulses = ace_state.lock().await.flight_pulses;
+                run_report.step_started(&instruction, flight_pulses);
+            }
+
+            drop(run_report);
+
+            // a hotbar key that never took would leave the step using the wrong item
+            let failed_slot_switch = {
+                let mut ace_state = ace_state.lock().await;
+                let failed = ace_state.failed_slot_switch.take();
+
+                // pressed afresh once the run is resumed
+                if failed.is_some() {
+                    ace_state.slot_switch = None;
+                }
+
+                failed
+            };
+
+            if let Some((slot, held)) = failed_slot_switch {
+                let error = hand_slot_error(slot, held);
+                span.in_scope(|| warn!(%error, "pausing the run"));
+                run.pause_on_error(&error, &tx);
+
+                report
+                    .lock()
+                    .await
+                    .incident(IncidentKind::HandSlot { slot, held });
+
+                run.publish(&mut *status.lock().await, None);
+                continue;
+            }
+
+            // control steps stay put, so nothing is steered on their ticks
+            if let Some(control) = &instruction.control {
+                let result = span
+                    .in_scope(|| run.run_control(&instruction, control, &minecraft_resource, &tx));
+
+                match result {
+                    Ok(false) => (),
+
+                    Ok(true) => {
+                        span.in_scope(|| info!(control = control.name(), "instruction done"));
+                        finish_step(&mut run, instruction, &report, &ace_state, &tx).await;
+                        metrics.lock().await.instructions_completed += 1;
+                    }
+
+                    Err(error) => {
+                        span.in_scope(|| warn!(%error, "step failed, pausing the run"));
+                        run.pause_on_error(&error, &tx);
+
+                        report
+                            .lock()
+                            .await
+                            .incident(IncidentKind::Condition { error });
+                    }
+                }
+
+                run.publish(&mut *status.lock().await, None);
+                continue;
+            }
+
+            // a script step heads for whatever its function picks on this tick
+            let step = instruction;
+            let (instruction, moves) = match &step.script {
+                None => (step.clone(), true),
+
+                Some(call) => match run.run_script(&step, call, &minecraft_resource) {
+                    Ok(Directive::Move {
+                        action,
+                        destination,
+                        looking,
+                    }) => (step.with_target(action, destination, looking), true),
+
+                    Ok(Directive::Look(yaw)) => {
+                        let position = minecraft_resource.player_position;
+                        let here = [position.x, position.y, position.z];
+
+                        (
+                            step.with_target(Action::Walk, here, Looking::Direction(yaw)),
+                            false,
+                        )
+                    }
+
+                    Ok(Directive::Wait) => {
+                        run.publish(&mut *status.lock().await, None);
+                        continue;
+                    }
+
+                    Ok(Directive::Done) => {
+                        span.in_scope(|| info!("script finished"));
+                        finish_step(&mut run, step, &report, &ace_state, &tx).await;
+
+                        metrics.lock().await.instructions_completed += 1;
+                        run.publish(&mut *status.lock().await, None);
+                        continue;
+                    }
+
+                    Err(error) => {
+                        span.in_scope(|| warn!(%error, "script failed, pausing the run"));
+                        run.pause_on_error(&error, &tx);
+
+                        report.lock().await.incident(IncidentKind::Script { error });
+
+                        run.publish(&mut *status.lock().await, None);
+                        continue;
+                    }
+                },
+            };
+
             let my_position = Vector3D::from(minecraft_resource.player_position);
             let goal_position = Vector3D::from(instruction.destination);
 
+            run.track_speed(&my_position);
+
             let mut horizontal_force = my_position
                 .horizontal_angle_distance_to(&goal_position, minecraft_resource.player_head.yaw);
 
@@ -40,21 +610,48 @@ pub fn spawn(minecraft_resource: Arc<Mutex<MinecraftResource>>, tx: Sender<Messa
                 _ => (),
             };
 
-            let vertical_angle = 5.0;
-
-            let vertical_force =
-                (minecraft_resource.player_head.y - vertical_angle).calculate_angle_force(32, -32);
+            let vertical_force = (minecraft_resource.player_head.y - run.parameters.vertical_angle)
+                .calculate_angle_force(32, -32);
 
             let head_movement = Message::HeadMovement {
                 horizontal_force: horizontal_force.calculate_angle_force(32, -32),
                 vertical_force,
             };
 
+            let head_corrected = matches!(
+                head_movement,
+                Message::HeadMovement { horizontal_force, vertical_force }
+                    if horizontal_force != 0 || vertical_force != 0
+            );
+
             tx.send(head_movement).unwrap();
-            tx.send(Message::Hand(instruction.clone())).unwrap();
+            tx.send(Message::Hand {
+                instruction: run.with_hand_slot(&instruction),
+                held_slot: minecraft_resource.held_slot(),
+            })
+            .unwrap();
 
             let distance = my_position.distance_to(&goal_position);
 
+            span.in_scope(|| {
+                debug!(
+                    distance,
+                    horizontal_force,
+                    vertical_force,
+                    position = ?minecraft_resource.player_position,
+                    "tick"
+                )
+            });
+
+            let mut run_report = report.lock().await;
+            run_report.tick(distance, head_corrected);
+
+            if let Some(updated_at) = minecraft_resource.updated_at {
+                run_report.telemetry_age(updated_at.elapsed());
+            }
+
+            drop(run_report);
+
             let fly_horizontal = Message::FlyHorizontal {
                 distance,
                 instruction: instruction.clone(),
@@ -65,19 +662,33 @@ pub fn spawn(minecraft_resource: Arc<Mutex<MinecraftResource>>, tx: Sender<Messa
                 instruction: instruction.clone(),
             };
 
-            tx.send(fly_horizontal).unwrap();
-            tx.send(fly_vertical).unwrap();
+            if moves {
+                tx.send(fly_horizontal).unwrap();
+                tx.send(fly_vertical).unwrap();
+            }
 
-            if distance < DISTANCE_TO_FINISH_INSTRUCTION {
-                instructions.pop();
-                let tx = tx.clone();
+            // script steps end when their function says so
+            let reached = step.script.is_none() && distance < run.parameters.distance_to_finish;
 
-                tokio::task::spawn(async move {
-                    sleep(TICKRATE_DURATION * 10).await;
+            if reached {
+                span.in_scope(|| info!(distance, "instruction reached"));
+                finish_step(&mut run, instruction, &report, &ace_state, &tx).await;
+            }
 
-                    let message = Message::InstructionFinished(instruction.clone());
-                    tx.send(message).unwrap();
-                });
+            run.publish(&mut *status.lock().await, Some(&my_position));
+
+            let tick_seconds = tick_started.elapsed();
+            let mut metrics = metrics.lock().await;
+
+            metrics.ticks += 1;
+            metrics.tick_seconds.observe(tick_seconds.as_secs_f64());
+
+            if tick_seconds > TICKRATE_DURATION {
+                metrics.tick_overruns += 1;
+            }
+
+            if reached {
+                metrics.instructions_completed += 1;
             }
         }
     });
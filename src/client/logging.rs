@@ -0,0 +1,22 @@
+use tracing_subscriber::{fmt, EnvFilter};
+
+/// Filter used when `RUST_LOG` is not set. Levels can be raised per module,
+/// e.g. `RUST_LOG=info,client::handlers=debug`
+const DEFAULT_FILTER: &str = "info";
+
+/// Installs the global subscriber, writing one JSON object per event when `json` is set
+pub fn init(json: bool) {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+
+    let subscriber = fmt().with_env_filter(filter);
+
+    match json {
+        true => subscriber
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .init(),
+        false => subscriber.init(),
+    }
+}
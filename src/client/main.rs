@@ -1,25 +1,49 @@
 mod ace_state;
+mod commands;
+mod config;
 mod handlers;
 mod instructions;
 mod instructions_task;
+mod logging;
 mod messages_task;
+mod metrics;
 mod minecraft_resource;
+mod plan;
+#[path = "../prometheus.rs"]
+mod prometheus;
+mod report;
+mod script;
+mod status;
+mod status_task;
+mod teach;
+#[path = "../url_query.rs"]
+mod url_query;
 mod vectors;
 
-use std::time::Duration;
+use std::{
+    env, process,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use ace_state::AceState;
-use actix_web::web::Bytes;
+use actix_web::{web, web::Bytes, App, HttpResponse, HttpServer};
 use actix_web_actors::ws;
-use futures_util::{SinkExt, StreamExt as _};
+use commands::{Ack, Incoming};
+use config::ClientConfig;
+use futures_util::{lock::Mutex, SinkExt, StreamExt as _};
 use handlers::message::Message;
+use metrics::ClientMetrics;
 use minecraft_resource::MinecraftResource;
+use report::{IncidentKind, Report, RunOutcome};
 use serde_json::Value;
+use status::Status;
 use tokio::{
     select,
-    sync::broadcast,
+    sync::{broadcast, mpsc},
     time::{sleep, timeout},
 };
+use tracing::{info, warn};
 
 use crate::minecraft_resource::{PlayerHead, PlayerPosition};
 
@@ -27,52 +51,185 @@ const TIMEOUT_DURATION: Duration = Duration::from_secs(1200);
 const TICKRATE_DURATION: Duration = Duration::from_millis(60);
 const WEBSOCKET_URL: &str = "ws://127.0.0.1:8080/ws";
 const DISTANCE_TO_FINISH_INSTRUCTION: f64 = 0.3;
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Address given with `--metrics`, if the Prometheus endpoint should be served
+fn metrics_address() -> Option<String> {
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == "--metrics" {
+            return args.next();
+        }
+    }
+
+    None
+}
+
+async fn metrics_route(metrics: web::Data<Arc<Mutex<ClientMetrics>>>) -> HttpResponse {
+    let metrics = metrics.lock().await;
+
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics.render())
+}
 
 #[actix_web::main]
 async fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.first().map(String::as_str) == Some("plan") {
+        if let Err(error) = plan::run(&args[1..]) {
+            eprintln!("{error}\n{}", plan::USAGE);
+            process::exit(2);
+        }
+
+        return;
+    }
+
+    logging::init(env::args().any(|arg| arg == "--log-json"));
+
+    if args.first().map(String::as_str) == Some("teach") {
+        if let Err(error) = teach::run(&args[1..]).await {
+            eprintln!("{error}\n{}", teach::USAGE);
+            process::exit(2);
+        }
+
+        return;
+    }
+
     let (tx, _) = broadcast::channel::<Message>(100);
 
-    let (_, mut ws) = awc::Client::new()
-        .ws(WEBSOCKET_URL)
-        .connect()
-        .await
-        .unwrap();
+    let metrics_shared = ClientMetrics::new_shared();
+
+    if let Some(address) = metrics_address() {
+        let metrics = metrics_shared.clone();
+
+        let metrics_server = HttpServer::new(move || {
+            App::new()
+                .app_data(web::Data::new(metrics.clone()))
+                .route("/metrics", web::get().to(metrics_route))
+        })
+        .workers(1)
+        .bind(&address)
+        .unwrap()
+        .run();
+
+        info!("serving metrics at http://{address}/metrics");
+        actix_web::rt::spawn(metrics_server);
+    }
+
+    let config = ClientConfig::load();
+    let (_, mut ws) = config.websocket_request().connect().await.unwrap();
 
     let ace_state_shared = AceState::new_shared();
     let minecraft_resource_shared = MinecraftResource::new_shared();
+    let status_shared = Status::new_shared();
+    let report_shared = Report::new_shared();
 
     let ws_minecraft_resource = minecraft_resource_shared.clone();
+    let ws_metrics = metrics_shared.clone();
+    let ws_report = report_shared.clone();
+    let timeout_ace_state = ace_state_shared.clone();
+    let timeout_report = report_shared.clone();
+
+    let (commands_tx, commands_rx) = mpsc::unbounded_channel();
+    let (outgoing_tx, mut outgoing_rx) = mpsc::unbounded_channel::<String>();
 
     let main_task = async move {
         messages_task::spawn(ace_state_shared.clone(), tx.clone());
-        instructions_task::spawn(minecraft_resource_shared.clone(), tx.clone());
+        instructions_task::spawn(
+            minecraft_resource_shared.clone(),
+            ace_state_shared.clone(),
+            metrics_shared.clone(),
+            status_shared.clone(),
+            report_shared.clone(),
+            tx.clone(),
+            commands_rx,
+            outgoing_tx.clone(),
+        );
+        status_task::spawn(
+            status_shared.clone(),
+            ace_state_shared.clone(),
+            outgoing_tx.clone(),
+        );
 
         loop {
             select! {
-              Some(websocket_message) = ws.next() => {
+              websocket_message = ws.next() => {
                 match websocket_message {
-                  Ok(ws::Frame::Ping(_)) => ws.send(ws::Message::Pong(Bytes::new())).await.unwrap(),
-                  Ok(ws::Frame::Text(text_received)) => {
-                    let output: Value = serde_json::from_slice(&text_received).unwrap();
+                  Some(Ok(ws::Frame::Ping(_))) => ws.send(ws::Message::Pong(Bytes::new())).await.unwrap(),
+                  Some(Ok(ws::Frame::Text(text_received))) => {
+                    let Ok(output) = serde_json::from_slice::<Value>(&text_received) else {
+                      continue;
+                    };
+
+                    if output.get("type").is_some() {
+                      match commands::parse(output) {
+                        Incoming::Command(frame) => commands_tx.send(frame).unwrap(),
+
+                        Incoming::Invalid { id, error } => {
+                          let ack = Ack { id, command: None, ok: false, error: Some(error) };
+                          outgoing_tx.send(ack.to_json()).unwrap();
+                        }
+
+                        Incoming::Ignored => (),
+                      }
+
+                      continue;
+                    }
+
+                    let (Some(x), Some(y), Some(z)) = (
+                      output["coords"]["x"].as_f64(),
+                      output["coords"]["y"].as_f64(),
+                      output["coords"]["z"].as_f64(),
+                    ) else {
+                      continue;
+                    };
 
                     let mut minecraft_resource = ws_minecraft_resource.lock().await;
+                    minecraft_resource.player_position = PlayerPosition::from((x, y, z));
+
+                    if let (Some(yaw), Some(head_y)) = (
+                      output["head"]["yaw"].as_f64(),
+                      output["head"]["y"].as_f64(),
+                    ) {
+                      minecraft_resource.player_head = PlayerHead { yaw, y: head_y };
+                    }
+
+                    minecraft_resource.updated_at = Some(Instant::now());
+                    minecraft_resource.telemetry = output;
+                    ws_metrics.lock().await.telemetry_frames += 1;
+                  },
+
+                  Some(Ok(ws::Frame::Close(_))) | Some(Err(_)) | None => {
+                    warn!("websocket closed, reconnecting");
+                    let mut attempts = 0;
 
-                    minecraft_resource.player_position = PlayerPosition::from((
-                      output["coords"]["x"].as_f64().unwrap(),
-                      output["coords"]["y"].as_f64().unwrap(),
-                      output["coords"]["z"].as_f64().unwrap(),
-                    ));
+                    ws = loop {
+                      attempts += 1;
+                      sleep(RECONNECT_DELAY).await;
 
-                    minecraft_resource.player_head = PlayerHead {
-                      yaw: output["head"]["yaw"].as_f64().unwrap(),
-                      y: output["head"]["y"].as_f64().unwrap(),
+                      match config.websocket_request().connect().await {
+                        Ok((_, ws)) => break ws,
+                        Err(error) => warn!(attempts, %error, "reconnect failed"),
+                      }
                     };
+
+                    info!(attempts, "websocket reconnected");
+                    ws_report.lock().await.incident(IncidentKind::Reconnect { attempts });
                   },
 
                   _ => ()
                 }
               },
 
+              Some(outgoing) = outgoing_rx.recv() => {
+                if let Err(error) = ws.send(ws::Message::Text(outgoing.into())).await {
+                  warn!(%error, "could not send a frame to the relay");
+                }
+              },
+
               _ = sleep(TIMEOUT_DURATION) => {
                 break;
               }
@@ -81,6 +238,9 @@ async fn main() {
     };
 
     if let Err(_) = timeout(TIMEOUT_DURATION, main_task).await {
-        println!("Terminated after timeout.");
+        info!("terminated after timeout");
+
+        let flight_pulses = timeout_ace_state.lock().await.flight_pulses;
+        report::close(&timeout_report, RunOutcome::Timeout, flight_pulses).await;
     }
 }
@@ -2,6 +2,7 @@ use enigo::*;
 use futures_util::lock::Mutex;
 use std::sync::Arc;
 use tokio::sync::broadcast::Sender;
+use tracing::{trace, Instrument};
 
 use crate::{
     ace_state::AceState,
@@ -10,25 +11,27 @@ use crate::{
         fly::{handle_fly_horizontal, handle_fly_vertical},
         hand::handle_hand,
         head_movement::handle_head_movement,
+        input::handle_input,
         message::Message,
     },
 };
 
 pub fn spawn(ace_state: Arc<Mutex<AceState>>, tx: Sender<Message>) {
-    let enigo = Arc::new(Mutex::new(Enigo::new()));
+    let backend = Arc::new(Mutex::new(Enigo::new()));
     let mut receiver = tx.subscribe();
 
     tokio::spawn(async move {
         loop {
-            let mut enigo = enigo.lock().await;
-            let mut ace_state = ace_state.lock().await;
             let message = receiver.recv().await.unwrap();
+            let mut enigo = backend.lock().await;
+            let mut ace_state = ace_state.lock().await;
 
             match message {
                 Message::HeadMovement {
                     horizontal_force,
                     vertical_force,
                 } => {
+                    trace!(horizontal_force, vertical_force, "head movement");
                     handle_head_movement(
                         &mut enigo,
                         &mut ace_state,
@@ -37,26 +40,53 @@ pub fn spawn(ace_state: Arc<Mutex<AceState>>, tx: Sender<Message>) {
                     );
                 }
 
-                Message::Hand(instruction) => {
-                    handle_hand(&mut enigo, &mut ace_state, instruction).await;
+                Message::Hand {
+                    instruction,
+                    held_slot,
+                } => {
+                    let span = instruction.span();
+                    handle_hand(&mut *enigo, &mut ace_state, instruction, held_slot)
+                        .instrument(span)
+                        .await;
                 }
 
                 Message::FlyHorizontal {
                     instruction,
                     distance,
                 } => {
-                    handle_fly_horizontal(&mut ace_state, instruction, distance).await;
+                    let span = instruction.span();
+                    handle_fly_horizontal(&mut ace_state, instruction, distance)
+                        .instrument(span)
+                        .await;
                 }
 
                 Message::FlyVertical {
                     instruction,
                     distance,
                 } => {
-                    handle_fly_vertical(&mut enigo, &mut ace_state, instruction, distance).await;
+                    let span = instruction.span();
+                    handle_fly_vertical(&mut enigo, &mut ace_state, instruction, distance)
+                        .instrument(span)
+                        .await;
+                }
+
+                Message::Input {
+                    instruction,
+                    held_slot,
+                } => {
+                    // holds and chats press from tasks of their own
+                    drop(enigo);
+
+                    let span = instruction.span();
+                    handle_input(&backend, &mut ace_state, instruction, held_slot)
+                        .instrument(span)
+                        .await;
                 }
 
                 Message::InstructionFinished(instruction) => {
-                    handle_finished(&mut enigo, &mut ace_state, &instruction);
+                    instruction
+                        .span()
+                        .in_scope(|| handle_finished(&mut enigo, &mut ace_state, &instruction));
                 }
             }
         }
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use futures_util::lock::Mutex;
+
+use crate::prometheus::{write_histogram, write_samples, Histogram};
+
+/// Upper bounds, in seconds, for the tick duration histogram
+const TICK_BUCKETS: [f64; 8] = [0.001, 0.005, 0.01, 0.02, 0.04, 0.06, 0.1, 0.25];
+
+/// Counters the client keeps for its optional `/metrics` endpoint
+#[derive(Debug)]
+pub struct ClientMetrics {
+    pub telemetry_frames: u64,
+    pub ticks: u64,
+    pub tick_overruns: u64,
+    pub tick_seconds: Histogram,
+    pub instructions_completed: u64,
+}
+
+impl ClientMetrics {
+    pub fn new_shared() -> Arc<Mutex<ClientMetrics>> {
+        Arc::new(Mutex::new(Self {
+            telemetry_frames: 0,
+            ticks: 0,
+            tick_overruns: 0,
+            tick_seconds: Histogram::new(&TICK_BUCKETS),
+            instructions_completed: 0,
+        }))
+    }
+
+    /// Renders every metric in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        write_samples(
+            &mut out,
+            "acecraft_client_telemetry_frames_total",
+            "counter",
+            "Telemetry frames received from the relay",
+            &[(String::new(), self.telemetry_frames as f64)],
+        );
+
+        write_samples(
+            &mut out,
+            "acecraft_client_ticks_total",
+            "counter",
+            "Instruction ticks evaluated",
+            &[(String::new(), self.ticks as f64)],
+        );
+
+        write_samples(
+            &mut out,
+            "acecraft_client_tick_overruns_total",
+            "counter",
+            "Ticks whose work took longer than the tickrate",
+            &[(String::new(), self.tick_overruns as f64)],
+        );
+
+        write_histogram(
+            &mut out,
+            "acecraft_client_tick_seconds",
+            "Time spent evaluating one tick, sleep excluded",
+            &self.tick_seconds,
+        );
+
+        write_samples(
+            &mut out,
+            "acecraft_client_instructions_completed_total",
+            "counter",
+            "Instructions whose destination was reached",
+            &[(String::new(), self.instructions_completed as f64)],
+        );
+
+        out
+    }
+}
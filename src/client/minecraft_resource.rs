@@ -1,6 +1,7 @@
 use futures_util::lock::Mutex;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use serde_json::Value;
+use std::{sync::Arc, time::Instant};
 
 #[derive(Debug, Clone, Copy, Deserialize, Serialize)]
 pub struct PlayerPosition {
@@ -26,10 +27,16 @@ pub struct PlayerHead {
 }
 
 /// Minecraft Resource are data that comes from the minecraft mod via websocket connection
-#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct MinecraftResource {
     pub player_position: PlayerPosition,
     pub player_head: PlayerHead,
+    /// When the last telemetry frame arrived
+    #[serde(skip)]
+    pub updated_at: Option<Instant>,
+    /// The last telemetry frame as it arrived, for conditions on its other fields
+    #[serde(skip)]
+    pub telemetry: Value,
 }
 
 impl MinecraftResource {
@@ -41,6 +48,39 @@ impl MinecraftResource {
                 z: 0.,
             },
             player_head: PlayerHead { yaw: 0., y: 0. },
+            updated_at: None,
+            telemetry: Value::Null,
         }))
     }
+
+    /// Hotbar slot held according to the telemetry, numbered from 1 like the
+    /// keys that select it while the game counts from 0 in `hand.slot`
+    pub fn held_slot(&self) -> Option<char> {
+        self.telemetry
+            .pointer("/hand/slot")
+            .and_then(Value::as_u64)
+            .filter(|slot| *slot < 9)
+            .map(|slot| char::from(b'1' + slot as u8))
+    }
+
+    /// A field of the telemetry by its dotted path, `x`, `y`, `z`, `yaw` and
+    /// `pitch` standing for the player position and head
+    pub fn field(&self, path: &str) -> Option<Value> {
+        let number = match path {
+            "x" => Some(self.player_position.x),
+            "y" => Some(self.player_position.y),
+            "z" => Some(self.player_position.z),
+            "yaw" => Some(self.player_head.yaw),
+            "pitch" => Some(self.player_head.y),
+            _ => None,
+        };
+
+        if let Some(number) = number {
+            return Some(Value::from(number));
+        }
+
+        path.split('.')
+            .try_fold(&self.telemetry, |value, key| value.get(key))
+            .cloned()
+    }
 }
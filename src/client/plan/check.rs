@@ -0,0 +1,28 @@
+use std::process;
+
+use crate::instructions::validate::validate;
+
+/// Prints every problem of a plan file, exiting with 1 when one is an error
+pub fn run(args: &[String]) -> Result<(), String> {
+    let [path] = args else {
+        return Err("check takes a single plan file".to_owned());
+    };
+
+    let report = validate(path);
+
+    for warning in &report.warnings {
+        eprintln!("warning: {warning}");
+    }
+
+    for error in &report.errors {
+        eprintln!("error: {error}");
+    }
+
+    match report.steps {
+        Some(steps) if report.errors.is_empty() => {
+            println!("{path}: {steps} steps");
+            Ok(())
+        }
+        _ => process::exit(1),
+    }
+}
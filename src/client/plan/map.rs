@@ -0,0 +1,364 @@
+use std::{fmt::Write, fs, path::Path};
+
+use crate::instructions::{load_instructions, Action, Instruction};
+
+use super::{trace, value};
+
+/// Distance in blocks from the planned path above which the driven path is highlighted
+const DEFAULT_THRESHOLD: f64 = 1.0;
+const SIZE: f64 = 1000.0;
+const MARGIN: f64 = 60.0;
+/// Smallest area shown, in blocks, so a short plan is not blown up
+const MIN_EXTENT: f64 = 16.0;
+
+const ACTUAL_COLOR: &str = "#e05d5d";
+const DEVIATION_COLOR: &str = "#ff8c1a";
+
+struct Options {
+    plan: String,
+    trace: Option<String>,
+    threshold: f64,
+    output: String,
+}
+
+/// Points of the driven path further than the threshold from the plan, in a row
+struct Deviation {
+    points: Vec<[f64; 3]>,
+    worst: ([f64; 3], f64),
+}
+
+/// Maps world x/z onto the picture, north (negative z) up and east right
+struct Projection {
+    x: f64,
+    z: f64,
+    scale: f64,
+}
+
+impl Projection {
+    /// Frames the points, or the smallest area around the origin when there are none
+    fn fit(points: &[[f64; 3]]) -> Self {
+        if points.is_empty() {
+            return Self {
+                x: 0.0,
+                z: 0.0,
+                scale: (SIZE - 2.0 * MARGIN) / MIN_EXTENT,
+            };
+        }
+
+        let (min_x, max_x) = bounds(points.iter().map(|point| point[0]));
+        let (min_z, max_z) = bounds(points.iter().map(|point| point[2]));
+        let extent = (max_x - min_x).max(max_z - min_z).max(MIN_EXTENT);
+
+        Self {
+            x: (min_x + max_x) / 2.0,
+            z: (min_z + max_z) / 2.0,
+            scale: (SIZE - 2.0 * MARGIN) / extent,
+        }
+    }
+
+    fn project(&self, point: &[f64; 3]) -> (f64, f64) {
+        (
+            SIZE / 2.0 + (point[0] - self.x) * self.scale,
+            SIZE / 2.0 + (point[2] - self.z) * self.scale,
+        )
+    }
+
+    fn polyline<'a>(&self, points: impl IntoIterator<Item = &'a [f64; 3]>) -> String {
+        points
+            .into_iter()
+            .map(|point| {
+                let (x, y) = self.project(point);
+                format!("{x:.1},{y:.1}")
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = parse_options(args)?;
+
+    let instructions = load_instructions(&options.plan)?;
+    let driven = match &options.trace {
+        Some(path) => trace::read(path)?,
+        None => Vec::new(),
+    };
+
+    let deviations = deviations(&instructions, &driven, options.threshold);
+    let svg = render(&instructions, &driven, &deviations, options.threshold);
+
+    fs::write(&options.output, svg)
+        .map_err(|error| format!("could not write {}: {error}", options.output))?;
+
+    println!(
+        "wrote {} ({} steps, {} positions, {} deviations above {} blocks)",
+        options.output,
+        instructions.len(),
+        driven.len(),
+        deviations.len(),
+        options.threshold
+    );
+
+    Ok(())
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut args = args.iter();
+
+    let plan = args
+        .next()
+        .ok_or_else(|| "missing plan file".to_owned())?
+        .to_owned();
+
+    let mut options = Options {
+        output: Path::new(&plan)
+            .with_extension("svg")
+            .to_string_lossy()
+            .into_owned(),
+        plan,
+        trace: None,
+        threshold: DEFAULT_THRESHOLD,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--trace" => options.trace = Some(value(&mut args, arg)?.to_owned()),
+            "--output" => options.output = value(&mut args, arg)?.to_owned(),
+            "--threshold" => {
+                options.threshold = value(&mut args, arg)?
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|threshold| *threshold > 0.0)
+                    .ok_or_else(|| "--threshold must be a positive number".to_owned())?;
+            }
+            _ => return Err(format!("unexpected argument {arg}")),
+        }
+    }
+
+    Ok(options)
+}
+
+fn bounds(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+        (min.min(value), max.max(value))
+    })
+}
+
+fn color(action: Action) -> &'static str {
+    match action {
+        Action::Walk => "#e0c34f",
+        Action::Fly => "#4fa3e0",
+        Action::RightClick => "#57d46b",
+        Action::Mine => "#b07cdb",
+    }
+}
+
+/// Horizontal distance from a point to the segment between two destinations
+fn distance_to_segment(point: &[f64; 3], from: &[f64; 3], to: &[f64; 3]) -> f64 {
+    let (dx, dz) = (to[0] - from[0], to[2] - from[2]);
+    let length = dx * dx + dz * dz;
+
+    let along = match length > 0.0 {
+        true => (((point[0] - from[0]) * dx + (point[2] - from[2]) * dz) / length).clamp(0.0, 1.0),
+        false => 0.0,
+    };
+
+    f64::hypot(
+        point[0] - from[0] - along * dx,
+        point[2] - from[2] - along * dz,
+    )
+}
+
+/// Horizontal distance from a point to the closest leg of the plan
+fn distance_to_plan(point: &[f64; 3], destinations: &[[f64; 3]]) -> f64 {
+    let first = distance_to_segment(point, &destinations[0], &destinations[0]);
+
+    destinations
+        .windows(2)
+        .map(|leg| distance_to_segment(point, &leg[0], &leg[1]))
+        .fold(first, f64::min)
+}
+
+fn deviations(instructions: &[Instruction], driven: &[[f64; 3]], threshold: f64) -> Vec<Deviation> {
+    let destinations: Vec<[f64; 3]> = instructions
+        .iter()
+        .map(|instruction| instruction.destination)
+        .collect();
+
+    if destinations.is_empty() {
+        return Vec::new();
+    }
+
+    let mut deviations = Vec::new();
+    let mut current: Option<Deviation> = None;
+
+    for point in driven {
+        let distance = distance_to_plan(point, &destinations);
+
+        if distance <= threshold {
+            deviations.extend(current.take());
+            continue;
+        }
+
+        let deviation = current.get_or_insert_with(|| Deviation {
+            points: Vec::new(),
+            worst: (*point, distance),
+        });
+
+        deviation.points.push(*point);
+
+        if distance > deviation.worst.1 {
+            deviation.worst = (*point, distance);
+        }
+    }
+
+    deviations.extend(current);
+    deviations
+}
+
+/// Plan names end up in the markup
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Length of the scale bar in blocks, a round number about a fifth of the picture
+fn scale_bar_length(scale: f64) -> f64 {
+    let target = SIZE / 5.0 / scale;
+    let magnitude = 10f64.powf(target.log10().floor());
+
+    [5.0, 2.0, 1.0]
+        .into_iter()
+        .map(|step| step * magnitude)
+        .find(|length| *length <= target)
+        .unwrap_or(magnitude)
+}
+
+fn render(
+    instructions: &[Instruction],
+    driven: &[[f64; 3]],
+    deviations: &[Deviation],
+    threshold: f64,
+) -> String {
+    let destinations: Vec<[f64; 3]> = instructions
+        .iter()
+        .map(|instruction| instruction.destination)
+        .collect();
+    let everything: Vec<[f64; 3]> = destinations.iter().chain(driven).copied().collect();
+    let projection = Projection::fit(&everything);
+
+    let mut svg = String::new();
+
+    let _ = writeln!(
+        svg,
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{SIZE}" height="{SIZE}" viewBox="0 0 {SIZE} {SIZE}" font-family="monospace" font-size="12">"##
+    );
+    let _ = writeln!(
+        svg,
+        r##"<rect width="100%" height="100%" fill="#1b1d22"/>"##
+    );
+
+    // planned legs, each coloured by the action that drives it
+    let _ = writeln!(
+        svg,
+        r#"<g id="plan" stroke-width="2" stroke-linecap="round">"#
+    );
+
+    for (leg, instruction) in destinations.windows(2).zip(instructions.iter().skip(1)) {
+        let _ = writeln!(
+            svg,
+            r#"  <polyline points="{}" stroke="{}" fill="none"/>"#,
+            projection.polyline(leg),
+            color(instruction.action)
+        );
+    }
+
+    let _ = writeln!(svg, "</g>");
+
+    if !driven.is_empty() {
+        let _ = writeln!(
+            svg,
+            r#"<polyline id="actual" points="{}" stroke="{ACTUAL_COLOR}" stroke-width="1" stroke-opacity="0.8" fill="none"/>"#,
+            projection.polyline(driven)
+        );
+    }
+
+    let _ = writeln!(
+        svg,
+        r#"<g id="deviations" stroke="{DEVIATION_COLOR}" fill="none">"#
+    );
+
+    for deviation in deviations {
+        let (x, y) = projection.project(&deviation.worst.0);
+        let [wx, wy, wz] = deviation.worst.0;
+
+        let _ = writeln!(
+            svg,
+            r#"  <polyline points="{}" stroke-width="4" stroke-opacity="0.6"/>"#,
+            projection.polyline(&deviation.points)
+        );
+        let _ = writeln!(
+            svg,
+            r#"  <circle cx="{x:.1}" cy="{y:.1}" r="8" stroke-width="2"><title>{:.2} blocks off at {wx:.1} {wy:.1} {wz:.1}</title></circle>"#,
+            deviation.worst.1
+        );
+    }
+
+    let _ = writeln!(svg, "</g>");
+
+    let _ = writeln!(svg, r#"<g id="waypoints">"#);
+
+    for instruction in instructions {
+        let (x, y) = projection.project(&instruction.destination);
+        let [dx, dy, dz] = instruction.destination;
+
+        let _ = writeln!(
+            svg,
+            r#"  <circle cx="{x:.1}" cy="{y:.1}" r="3" fill="{}"><title>{} {} {} -> {dx} {dy} {dz}</title></circle>"#,
+            color(instruction.action),
+            instruction.step + 1,
+            escape(&instruction.name),
+            instruction.kind()
+        );
+    }
+
+    let _ = writeln!(svg, "</g>");
+
+    let legend = [
+        (color(Action::Walk), "walk".to_owned()),
+        (color(Action::Fly), "fly".to_owned()),
+        (color(Action::RightClick), "right_clicking".to_owned()),
+        (color(Action::Mine), "mining".to_owned()),
+        (ACTUAL_COLOR, "actual".to_owned()),
+        (
+            DEVIATION_COLOR,
+            format!("off by more than {threshold} blocks"),
+        ),
+    ];
+
+    for (index, (color, label)) in legend.iter().enumerate() {
+        let y = 20.0 + index as f64 * 18.0;
+
+        let _ = writeln!(
+            svg,
+            r##"<line x1="12" y1="{y}" x2="36" y2="{y}" stroke="{color}" stroke-width="3"/><text x="44" y="{}" fill="#ddd">{label}</text>"##,
+            y + 4.0
+        );
+    }
+
+    let length = scale_bar_length(projection.scale);
+    let bar = SIZE - 16.0;
+    let width = length * projection.scale;
+
+    let _ = writeln!(
+        svg,
+        r##"<line x1="12" y1="{bar}" x2="{:.1}" y2="{bar}" stroke="#ddd" stroke-width="2"/><text x="12" y="{}" fill="#ddd">{length} blocks, north up</text>"##,
+        12.0 + width,
+        bar - 8.0
+    );
+
+    let _ = writeln!(svg, "</svg>");
+
+    svg
+}
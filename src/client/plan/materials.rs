@@ -0,0 +1,224 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use tracing::warn;
+
+use crate::{
+    config::PlannerConfig,
+    instructions::{load_instructions, Action, Instruction},
+};
+
+use super::{
+    stats::{legs, Leg},
+    value,
+};
+
+const STACK_SIZE: u64 = 64;
+
+/// Blocks expected to be placed with one hand slot
+#[derive(Debug, Default)]
+pub struct SlotEstimate {
+    pub legs: usize,
+    pub blocks: u64,
+}
+
+/// A hand slot whose estimate exceeds what the inventory holds
+#[derive(Debug)]
+pub struct Shortfall {
+    pub hand_slot: char,
+    pub needed: u64,
+    pub held: u64,
+}
+
+/// Blocks placed along a right-click leg
+fn leg_blocks(leg: &Leg, planner: &PlannerConfig) -> u64 {
+    let blocks = match planner.placement_rate {
+        Some(rate) => leg.seconds(planner) * rate,
+        None => leg.horizontal,
+    };
+
+    blocks.ceil() as u64
+}
+
+fn placing_legs(instructions: &[Instruction]) -> impl Iterator<Item = Leg> {
+    legs(instructions)
+        .into_iter()
+        .filter(|leg| matches!(leg.action, Action::RightClick))
+}
+
+pub fn estimate(
+    instructions: &[Instruction],
+    planner: &PlannerConfig,
+) -> BTreeMap<char, SlotEstimate> {
+    let mut slots: BTreeMap<char, SlotEstimate> = BTreeMap::new();
+
+    for leg in placing_legs(instructions) {
+        let slot = slots.entry(leg.hand_slot).or_default();
+
+        slot.legs += 1;
+        slot.blocks += leg_blocks(&leg, planner);
+    }
+
+    slots
+}
+
+/// Slots that would run out, only checked when an inventory is configured
+pub fn shortfalls(
+    estimate: &BTreeMap<char, SlotEstimate>,
+    inventory: &BTreeMap<char, u64>,
+) -> Vec<Shortfall> {
+    if inventory.is_empty() {
+        return Vec::new();
+    }
+
+    estimate
+        .iter()
+        .map(|(hand_slot, slot)| Shortfall {
+            hand_slot: *hand_slot,
+            needed: slot.blocks,
+            held: inventory.get(hand_slot).copied().unwrap_or(0),
+        })
+        .filter(|shortfall| shortfall.needed > shortfall.held)
+        .collect()
+}
+
+/// Warns before a run when the inventory cannot finish the plan. The
+/// inventory is the `[planner.inventory]` table of the config, not what the
+/// player actually carries, which the telemetry does not report
+pub fn check_inventory(instructions: &[Instruction]) -> Result<(), String> {
+    let planner = PlannerConfig::load()?;
+    let estimate = estimate(instructions, &planner);
+
+    for shortfall in shortfalls(&estimate, &planner.inventory) {
+        warn!(
+            hand_slot = %shortfall.hand_slot,
+            needed = shortfall.needed,
+            held = shortfall.held,
+            "not enough blocks for the plan"
+        );
+    }
+
+    Ok(())
+}
+
+fn stacks(blocks: u64) -> String {
+    match blocks % STACK_SIZE {
+        0 => format!("{}", blocks / STACK_SIZE),
+        rest => format!("{} + {rest}", blocks / STACK_SIZE),
+    }
+}
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut args = args.iter();
+    let path = args.next().ok_or_else(|| "missing plan file".to_owned())?;
+    let mut planner = PlannerConfig::load()?;
+    let mut list_legs = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--legs" => list_legs = true,
+            "--rate" => {
+                let rate = value(&mut args, arg)?
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|rate| *rate > 0.0)
+                    .ok_or_else(|| "--rate must be a positive number".to_owned())?;
+
+                planner.placement_rate = Some(rate);
+            }
+            "--inventory" => {
+                let held = value(&mut args, arg)?;
+
+                let (slot, count) = held
+                    .split_once('=')
+                    .and_then(|(slot, count)| {
+                        Some((slot.parse::<char>().ok()?, count.parse::<u64>().ok()?))
+                    })
+                    .ok_or_else(|| format!("--inventory expects <slot>=<count>, got {held}"))?;
+
+                planner.inventory.insert(slot, count);
+            }
+            _ => return Err(format!("unexpected argument {arg}")),
+        }
+    }
+
+    let instructions = load_instructions(path)?;
+    print!("{}", summary(path, &instructions, &planner, list_legs));
+
+    Ok(())
+}
+
+fn summary(
+    path: &str,
+    instructions: &[Instruction],
+    planner: &PlannerConfig,
+    list_legs: bool,
+) -> String {
+    let mut text = String::new();
+    let estimate = estimate(instructions, planner);
+
+    let basis = match planner.placement_rate {
+        Some(rate) => format!("{rate} blocks/s"),
+        None => "one block per traversed block".to_owned(),
+    };
+
+    let _ = writeln!(text, "Plan {path}, placing {basis}");
+
+    if list_legs {
+        let _ = writeln!(text);
+        let _ = writeln!(
+            text,
+            "{:>4}  {:>4} {:>9} {:>7}",
+            "step", "slot", "distance", "blocks"
+        );
+
+        for leg in placing_legs(instructions) {
+            let _ = writeln!(
+                text,
+                "{:>4}  {:>4} {:>9.1} {:>7}",
+                leg.step + 1,
+                leg.hand_slot,
+                leg.horizontal,
+                leg_blocks(&leg, planner)
+            );
+        }
+    }
+
+    let _ = writeln!(text);
+    let _ = writeln!(
+        text,
+        "{:>4} {:>5} {:>7} {:>12} {:>6}",
+        "slot", "legs", "blocks", "stacks", "held"
+    );
+
+    for (hand_slot, slot) in &estimate {
+        let held = planner
+            .inventory
+            .get(hand_slot)
+            .map_or("-".to_owned(), |held| held.to_string());
+
+        let _ = writeln!(
+            text,
+            "{hand_slot:>4} {:>5} {:>7} {:>12} {held:>6}",
+            slot.legs,
+            slot.blocks,
+            stacks(slot.blocks)
+        );
+    }
+
+    let total: u64 = estimate.values().map(|slot| slot.blocks).sum();
+    let _ = writeln!(text);
+    let _ = writeln!(text, "Total {total} blocks, {} stacks", stacks(total));
+
+    for shortfall in shortfalls(&estimate, &planner.inventory) {
+        let _ = writeln!(
+            text,
+            "warning: slot {} needs {} blocks but the inventory holds {}, {} short",
+            shortfall.hand_slot,
+            shortfall.needed,
+            shortfall.held,
+            shortfall.needed - shortfall.held
+        );
+    }
+
+    text
+}
@@ -0,0 +1,56 @@
+mod check;
+mod map;
+pub mod materials;
+mod order;
+mod simplify;
+mod simulate;
+mod stats;
+mod trace;
+mod transform;
+pub mod waypoints;
+
+pub const USAGE: &str = "usage:
+  client plan check <plan.toml>
+  client plan map <plan.toml> [--trace <recording.jsonl>] [--threshold <blocks>] [--output <map.svg>]
+  client plan stats <plan.toml> [--walk-speed <blocks/s>] [--sprint-speed <blocks/s>] [--fly-speed <blocks/s>] [--vertical-speed <blocks/s>]
+  client plan materials <plan.toml> [--rate <blocks/s>] [--inventory <slot>=<count>]... [--legs]
+  client plan order <targets.toml> [--visit <action>] [--name <name>] [--hand-slot <slot>] [--output <plan.toml>]
+  client plan simulate <plan.toml> [--start <x,y,z>] [--limit <seconds>] [--telemetry <json>]
+  client plan simplify <recording.jsonl> [--tolerance <blocks>] [--snap] [--action <action>] [--name <name>] [--hand-slot <slot>] [--output <plan.toml>]
+  client plan transform <plan.toml> [--translate <x,y,z>] [--rotate <degrees>] [--mirror x|z] [--reverse] [--pivot <x,y,z>] [--output <plan.toml>]
+
+Transforms apply in the order given, around the first destination unless --pivot is set.
+Speeds, placement rate and inventory default to the [planner] table of config.toml";
+
+/// Runs `client plan ...`, the offline tools that only read files and never
+/// touch the game or the relay
+pub fn run(args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        Some("check") => check::run(&args[1..]),
+        Some("map") => map::run(&args[1..]),
+        Some("stats") => stats::run(&args[1..]),
+        Some("materials") => materials::run(&args[1..]),
+        Some("order") => order::run(&args[1..]),
+        Some("simulate") => simulate::run(&args[1..]),
+        Some("simplify") => simplify::run(&args[1..]),
+        Some("transform") => transform::run(&args[1..]),
+        Some(command) => Err(format!("unknown plan command {command}")),
+        None => Err("missing plan command".to_owned()),
+    }
+}
+
+/// Value following a flag, or an error naming the flag
+fn value<'a>(args: &mut impl Iterator<Item = &'a String>, flag: &str) -> Result<&'a str, String> {
+    args.next()
+        .map(String::as_str)
+        .ok_or_else(|| format!("missing value for {flag}"))
+}
+
+/// `x,y,z` given to a flag
+fn position(text: &str, flag: &str) -> Result<[f64; 3], String> {
+    text.split(',')
+        .map(|coordinate| coordinate.trim().parse::<f64>().ok())
+        .collect::<Option<Vec<f64>>>()
+        .and_then(|coords| <[f64; 3]>::try_from(coords).ok())
+        .ok_or_else(|| format!("{flag} expects x,y,z, got {text}"))
+}
@@ -0,0 +1,224 @@
+use std::fs;
+
+use serde::Deserialize;
+
+use crate::{
+    config::PlannerConfig,
+    instructions::{Action, Looking},
+};
+
+use super::{
+    stats::Leg,
+    value,
+    waypoints::{plan_file, Waypoint},
+};
+
+/// Height difference above which a move is flown rather than walked
+const FLY_ABOVE: f64 = 1.0;
+/// Passes of 2-opt before settling for the order found so far
+const MAX_PASSES: usize = 50;
+
+/// Positions to visit in any order, from a file like
+/// `targets = [[15306.5, 44.0, 15223.5], ...]` with an optional `start`
+#[derive(Deserialize)]
+struct Targets {
+    start: Option<[f64; 3]>,
+    targets: Vec<[f64; 3]>,
+}
+
+struct Options {
+    targets: String,
+    visit: Action,
+    name: String,
+    hand_slot: char,
+    output: Option<String>,
+}
+
+fn travel(from: &[f64; 3], to: &[f64; 3]) -> Leg {
+    let vertical = to[1] - from[1];
+
+    Leg {
+        step: 0,
+        action: match vertical.abs() > FLY_ABOVE {
+            true => Action::Fly,
+            false => Action::Walk,
+        },
+        hand_slot: '1',
+        horizontal: f64::hypot(to[0] - from[0], to[2] - from[2]),
+        vertical,
+    }
+}
+
+/// Seconds between every two positions
+fn costs(positions: &[[f64; 3]], speeds: &PlannerConfig) -> Vec<Vec<f64>> {
+    positions
+        .iter()
+        .map(|from| {
+            positions
+                .iter()
+                .map(|to| travel(from, to).seconds(speeds))
+                .collect()
+        })
+        .collect()
+}
+
+fn route_cost(route: &[usize], costs: &[Vec<f64>]) -> f64 {
+    route.windows(2).map(|pair| costs[pair[0]][pair[1]]).sum()
+}
+
+/// Always moves on to the closest position not visited yet
+fn nearest_neighbour(costs: &[Vec<f64>]) -> Vec<usize> {
+    let mut route = vec![0];
+    let mut left: Vec<usize> = (1..costs.len()).collect();
+
+    while let Some(last) = route.last().copied() {
+        let Some((index, _)) = left
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| costs[last][**a].total_cmp(&costs[last][**b]))
+        else {
+            break;
+        };
+
+        route.push(left.swap_remove(index));
+    }
+
+    route
+}
+
+/// Reverses stretches of the route while that makes it cheaper. The route is
+/// open and its first position stays where it is.
+fn two_opt(route: &mut [usize], costs: &[Vec<f64>]) {
+    for _ in 0..MAX_PASSES {
+        let mut improved = false;
+
+        for first in 1..route.len() {
+            for last in first + 1..route.len() {
+                let before = route[first - 1];
+                let after = route.get(last + 1).copied();
+
+                let removed = costs[before][route[first]]
+                    + after.map_or(0.0, |after| costs[route[last]][after]);
+                let added = costs[before][route[last]]
+                    + after.map_or(0.0, |after| costs[route[first]][after]);
+
+                if added + 1e-9 < removed {
+                    route[first..=last].reverse();
+                    improved = true;
+                }
+            }
+        }
+
+        if !improved {
+            break;
+        }
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut args = args.iter();
+
+    let mut options = Options {
+        targets: args
+            .next()
+            .ok_or_else(|| "missing targets file".to_owned())?
+            .to_owned(),
+        visit: Action::RightClick,
+        name: "Ordered".to_owned(),
+        hand_slot: '1',
+        output: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--visit" => {
+                let action = value(&mut args, arg)?;
+                options.visit =
+                    Action::parse(action).ok_or_else(|| format!("unknown action {action}"))?;
+            }
+            "--name" => options.name = value(&mut args, arg)?.to_owned(),
+            "--hand-slot" => {
+                options.hand_slot = value(&mut args, arg)?
+                    .parse::<char>()
+                    .map_err(|_| "--hand-slot expects a single character".to_owned())?;
+            }
+            "--output" => options.output = Some(value(&mut args, arg)?.to_owned()),
+            _ => return Err(format!("unexpected argument {arg}")),
+        }
+    }
+
+    Ok(options)
+}
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = parse_options(args)?;
+    let speeds = PlannerConfig::load()?;
+
+    let source = fs::read_to_string(&options.targets)
+        .map_err(|error| format!("could not read {}: {error}", options.targets))?;
+    let targets: Targets =
+        toml::from_str(&source).map_err(|error| format!("{}: {error}", options.targets))?;
+
+    if targets.targets.is_empty() {
+        return Err(format!("{} has no targets", options.targets));
+    }
+
+    // the route starts at `start` when given, otherwise at the first target
+    let positions: Vec<[f64; 3]> = targets
+        .start
+        .iter()
+        .chain(&targets.targets)
+        .copied()
+        .collect();
+    let costs = costs(&positions, &speeds);
+
+    let mut route = nearest_neighbour(&costs);
+    let greedy_cost = route_cost(&route, &costs);
+
+    two_opt(&mut route, &costs);
+
+    let mut waypoints = Vec::new();
+    let mut from = positions[route[0]];
+
+    for index in route.iter().skip(usize::from(targets.start.is_some())) {
+        let target = positions[*index];
+
+        if target != from {
+            waypoints.push(Waypoint {
+                action: travel(&from, &target).action,
+                destination: target,
+                looking: Looking::Front,
+                script: None,
+                control: None,
+            });
+        }
+
+        waypoints.push(Waypoint {
+            action: options.visit,
+            destination: target,
+            looking: Looking::Front,
+            script: None,
+            control: None,
+        });
+
+        from = target;
+    }
+
+    let plan = plan_file(&options.name, options.hand_slot, &waypoints);
+
+    match &options.output {
+        Some(path) => {
+            fs::write(path, plan).map_err(|error| format!("could not write {path}: {error}"))?
+        }
+        None => print!("{plan}"),
+    }
+
+    eprintln!(
+        "ordered {} targets, about {:.0}s of travel ({:.0}s nearest neighbour only)",
+        targets.targets.len(),
+        route_cost(&route, &costs),
+        greedy_cost
+    );
+
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use std::fs;
+
+use crate::instructions::{Action, Looking};
+
+use super::{
+    trace, value,
+    waypoints::{plan_file, round, snap, Waypoint},
+};
+
+/// Distance in blocks a dropped position may be from the simplified path
+const DEFAULT_TOLERANCE: f64 = 0.5;
+/// Waypoints closer than this to the line through their neighbours are merged
+const COLLINEAR_TOLERANCE: f64 = 0.01;
+
+struct Options {
+    trace: String,
+    tolerance: f64,
+    snap: bool,
+    action: Action,
+    name: String,
+    hand_slot: char,
+    output: Option<String>,
+}
+
+fn distance_to_segment(point: &[f64; 3], from: &[f64; 3], to: &[f64; 3]) -> f64 {
+    let segment = [to[0] - from[0], to[1] - from[1], to[2] - from[2]];
+    let offset = [point[0] - from[0], point[1] - from[1], point[2] - from[2]];
+    let length: f64 = segment.iter().map(|axis| axis * axis).sum();
+
+    let along = match length > 0.0 {
+        true => {
+            let dot: f64 = segment.iter().zip(offset).map(|(a, b)| a * b).sum();
+            (dot / length).clamp(0.0, 1.0)
+        }
+        false => 0.0,
+    };
+
+    (0..3)
+        .map(|axis| offset[axis] - along * segment[axis])
+        .map(|axis| axis * axis)
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Ramer–Douglas–Peucker: keeps the positions the path bends at, so that no
+/// dropped position is further than the tolerance from what is kept
+pub fn simplify(points: &[[f64; 3]], tolerance: f64) -> Vec<[f64; 3]> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; points.len()];
+    let mut ranges = vec![(0, points.len() - 1)];
+
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    while let Some((first, last)) = ranges.pop() {
+        let farthest = (first + 1..last)
+            .map(|index| {
+                let distance = distance_to_segment(&points[index], &points[first], &points[last]);
+                (index, distance)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        if let Some((index, distance)) = farthest {
+            if distance > tolerance {
+                keep[index] = true;
+                ranges.push((first, index));
+                ranges.push((index, last));
+            }
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(point, keep)| keep.then_some(*point))
+        .collect()
+}
+
+/// Drops repeated waypoints and the middle of straight runs, which snapping
+/// to block centres tends to leave behind
+pub fn merge_collinear(points: &[[f64; 3]]) -> Vec<[f64; 3]> {
+    let mut merged: Vec<[f64; 3]> = Vec::with_capacity(points.len());
+
+    for point in points {
+        if merged.last() == Some(point) {
+            continue;
+        }
+
+        while let [.., before, middle] = merged[..] {
+            if distance_to_segment(&middle, &before, point) > COLLINEAR_TOLERANCE {
+                break;
+            }
+
+            merged.pop();
+        }
+
+        merged.push(*point);
+    }
+
+    merged
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut args = args.iter();
+
+    let mut options = Options {
+        trace: args
+            .next()
+            .ok_or_else(|| "missing trace to simplify".to_owned())?
+            .to_owned(),
+        tolerance: DEFAULT_TOLERANCE,
+        snap: false,
+        action: Action::Walk,
+        name: "Simplified".to_owned(),
+        hand_slot: '1',
+        output: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--snap" => options.snap = true,
+            "--tolerance" => {
+                options.tolerance = value(&mut args, arg)?
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|tolerance| *tolerance >= 0.0)
+                    .ok_or_else(|| "--tolerance must be a number of blocks".to_owned())?;
+            }
+            "--action" => {
+                let action = value(&mut args, arg)?;
+                options.action =
+                    Action::parse(action).ok_or_else(|| format!("unknown action {action}"))?;
+            }
+            "--name" => options.name = value(&mut args, arg)?.to_owned(),
+            "--hand-slot" => {
+                options.hand_slot = value(&mut args, arg)?
+                    .parse::<char>()
+                    .map_err(|_| "--hand-slot expects a single character".to_owned())?;
+            }
+            "--output" => options.output = Some(value(&mut args, arg)?.to_owned()),
+            _ => return Err(format!("unexpected argument {arg}")),
+        }
+    }
+
+    Ok(options)
+}
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = parse_options(args)?;
+    let positions = trace::read(&options.trace)?;
+
+    let simplified: Vec<[f64; 3]> = simplify(&positions, options.tolerance)
+        .into_iter()
+        .map(|position| match options.snap {
+            true => snap(position),
+            false => round(position),
+        })
+        .collect();
+
+    let waypoints: Vec<Waypoint> = merge_collinear(&simplified)
+        .into_iter()
+        .map(|destination| Waypoint {
+            action: options.action,
+            destination,
+            looking: Looking::Front,
+            script: None,
+            control: None,
+        })
+        .collect();
+
+    let plan = plan_file(&options.name, options.hand_slot, &waypoints);
+
+    match &options.output {
+        Some(path) => {
+            fs::write(path, plan).map_err(|error| format!("could not write {path}: {error}"))?
+        }
+        None => print!("{plan}"),
+    }
+
+    eprintln!(
+        "simplified {} positions into {} waypoints",
+        positions.len(),
+        waypoints.len()
+    );
+
+    Ok(())
+}
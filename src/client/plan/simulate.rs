@@ -0,0 +1,320 @@
+use rhai::{Dynamic, Map};
+use serde_json::Value;
+
+use crate::{
+    config::PlannerConfig,
+    instructions::{control::Control, load_instructions, Action, Instruction, Looking, ScriptCall},
+    logging,
+    minecraft_resource::{MinecraftResource, PlayerHead, PlayerPosition},
+    script::Directive,
+    DISTANCE_TO_FINISH_INSTRUCTION, TICKRATE_DURATION,
+};
+
+use super::{position, stats::format_duration, value};
+
+/// Simulated seconds after which a plan is given up on, in case a script
+/// step never finishes
+const DEFAULT_LIMIT: f64 = 3600.0;
+
+struct Options {
+    plan: String,
+    start: Option<[f64; 3]>,
+    limit: f64,
+    /// Fields conditions can check besides the position and the head
+    telemetry: Value,
+}
+
+/// A player that moves in a straight line at the planner speeds and turns
+/// instantly, ticking like the client does
+struct Simulator {
+    resource: MinecraftResource,
+    seconds: f64,
+    limit: f64,
+}
+
+impl Simulator {
+    fn new(start: [f64; 3], limit: f64, telemetry: Value) -> Self {
+        Self {
+            resource: MinecraftResource {
+                player_position: PlayerPosition::from((start[0], start[1], start[2])),
+                player_head: PlayerHead { yaw: 0.0, y: 0.0 },
+                updated_at: None,
+                telemetry,
+            },
+            seconds: 0.0,
+            limit,
+        }
+    }
+
+    fn position(&self) -> [f64; 3] {
+        let position = self.resource.player_position;
+        [position.x, position.y, position.z]
+    }
+
+    fn tick(&mut self) -> Result<(), String> {
+        self.seconds += TICKRATE_DURATION.as_secs_f64();
+
+        match self.seconds > self.limit {
+            true => Err(format!(
+                "still running after {}, see --limit",
+                format_duration(self.limit)
+            )),
+            false => Ok(()),
+        }
+    }
+
+    /// Moves one tick toward `destination`, returning how far it still is
+    fn advance(
+        &mut self,
+        action: Action,
+        destination: [f64; 3],
+        looking: Looking,
+        speeds: &PlannerConfig,
+    ) -> f64 {
+        let [x, y, z] = self.position();
+        let (dx, dy, dz) = (destination[0] - x, destination[1] - y, destination[2] - z);
+        let horizontal = f64::hypot(dx, dz);
+        let seconds = TICKRATE_DURATION.as_secs_f64();
+
+        let speed = match action {
+            Action::Walk if horizontal > speeds.sprint_distance => speeds.sprint_speed,
+            Action::Walk | Action::RightClick | Action::Mine => speeds.walk_speed,
+            Action::Fly => speeds.fly_speed,
+        };
+
+        let along = match horizontal > 0.0 {
+            true => (speed * seconds / horizontal).min(1.0),
+            false => 1.0,
+        };
+        let climb = dy.clamp(
+            -speeds.vertical_speed * seconds,
+            speeds.vertical_speed * seconds,
+        );
+
+        self.resource.player_position =
+            PlayerPosition::from((x + dx * along, y + climb, z + dz * along));
+
+        // yaw as the game counts it, 0 facing south and growing clockwise
+        let heading = (-dx).atan2(dz).to_degrees();
+
+        self.resource.player_head.yaw = match looking {
+            Looking::Direction(yaw) => yaw,
+            Looking::Back if horizontal > 0.0 => heading + 180.0,
+            Looking::Front if horizontal > 0.0 => heading,
+            _ => self.resource.player_head.yaw,
+        };
+
+        let [x, y, z] = self.position();
+        f64::hypot(
+            f64::hypot(destination[0] - x, destination[2] - z),
+            destination[1] - y,
+        )
+    }
+
+    fn run_step(
+        &mut self,
+        instruction: &Instruction,
+        speeds: &PlannerConfig,
+    ) -> Result<(), String> {
+        match (&instruction.script, &instruction.control) {
+            (Some(call), _) => self.run_script(call, speeds),
+            (None, Some(control)) => self.run_control(control),
+
+            (None, None) => {
+                while self.advance(
+                    instruction.action,
+                    instruction.destination,
+                    instruction.looking,
+                    speeds,
+                ) >= DISTANCE_TO_FINISH_INSTRUCTION
+                {
+                    self.tick()?;
+                }
+
+                self.tick()
+            }
+        }
+    }
+
+    /// Waits and inputs take their time, wait_until ticks until its condition
+    /// holds. An if takes no time, its branch being picked by the caller.
+    fn run_control(&mut self, control: &Control) -> Result<(), String> {
+        let started_at = self.seconds;
+
+        if let Some(duration) = control.duration() {
+            while self.seconds - started_at < duration.as_secs_f64() {
+                self.tick()?;
+            }
+
+            return Ok(());
+        }
+
+        if let Control::WaitUntil { condition, timeout } = control {
+            while !condition.holds(&self.resource).unwrap_or(false) {
+                if self.seconds - started_at >= timeout.as_secs_f64() {
+                    return Err(format!(
+                        "{condition} still false after {:.1}s",
+                        timeout.as_secs_f64()
+                    ));
+                }
+
+                self.tick()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Calls the script every tick as the client would, printing what it asks
+    /// for whenever that changes
+    fn run_script(&mut self, call: &ScriptCall, speeds: &PlannerConfig) -> Result<(), String> {
+        let mut memory = Dynamic::from_map(Map::new());
+        let mut last = String::new();
+
+        for ticks in 0.. {
+            let directive =
+                call.scripts
+                    .call(&call.function, &self.resource, ticks, &mut memory)?;
+
+            let description = match directive {
+                Directive::Move {
+                    action,
+                    destination: [x, y, z],
+                    ..
+                } => format!("{} to [{x:.2}, {y:.2}, {z:.2}]", action.name()),
+                Directive::Look(yaw) => format!("look {yaw}"),
+                Directive::Wait => "wait".to_owned(),
+                Directive::Done => "done".to_owned(),
+            };
+
+            if description != last {
+                println!(
+                    "{:>9}    {}: {description}",
+                    format_duration(self.seconds),
+                    call.function
+                );
+                last = description;
+            }
+
+            match directive {
+                Directive::Move {
+                    action,
+                    destination,
+                    looking,
+                } => {
+                    self.advance(action, destination, looking, speeds);
+                }
+                Directive::Look(yaw) => self.resource.player_head.yaw = yaw,
+                Directive::Wait => (),
+                Directive::Done => return Ok(()),
+            }
+
+            self.tick()?;
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut args = args.iter();
+
+    let mut options = Options {
+        plan: args
+            .next()
+            .ok_or_else(|| "missing plan file".to_owned())?
+            .to_owned(),
+        start: None,
+        limit: DEFAULT_LIMIT,
+        telemetry: Value::Null,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--start" => options.start = Some(position(value(&mut args, arg)?, arg)?),
+            "--limit" => {
+                options.limit = value(&mut args, arg)?
+                    .parse::<f64>()
+                    .map_err(|_| "--limit expects seconds".to_owned())?;
+            }
+            "--telemetry" => {
+                options.telemetry = serde_json::from_str(value(&mut args, arg)?)
+                    .map_err(|error| format!("--telemetry expects a JSON object: {error}"))?;
+            }
+            _ => return Err(format!("unexpected argument {arg}")),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Runs a plan against the simulator, script steps included, so scripts can be
+/// tried without the game
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = parse_options(args)?;
+    let speeds = PlannerConfig::load()?;
+    let instructions = load_instructions(&options.plan)?;
+
+    // shows what scripts print
+    logging::init(false);
+
+    let Some(start) = options
+        .start
+        .or(instructions.first().map(|first| first.destination))
+    else {
+        return Err(format!("{} has no steps", options.plan));
+    };
+
+    let mut simulator = Simulator::new(start, options.limit, options.telemetry);
+
+    // last first like the client keeps them, so an if can drop a branch
+    let mut remaining: Vec<&Instruction> = instructions.iter().rev().collect();
+    let mut count = 0;
+
+    while let Some(instruction) = remaining.pop() {
+        let in_step =
+            |error: String| format!("step {} ({}): {error}", instruction.step, instruction.name);
+
+        simulator.run_step(instruction, &speeds).map_err(in_step)?;
+        count += 1;
+
+        if let Some(Control::If {
+            condition,
+            then_steps,
+            else_steps,
+        }) = &instruction.control
+        {
+            let holds = condition
+                .holds(&simulator.resource)
+                .map_err(|error| in_step(format!("if {condition}: {error}")))?;
+
+            let end = remaining.len() - then_steps;
+
+            match holds {
+                true => remaining.drain(end - else_steps..end),
+                false => remaining.drain(end..end + then_steps),
+            };
+        }
+
+        let [x, y, z] = simulator.position();
+        let action = match &instruction.script {
+            Some(call) => &call.function,
+            None => instruction.kind(),
+        };
+
+        println!(
+            "{:>9}  step {:>3} {action:<15} {} at [{x:.1}, {y:.1}, {z:.1}]",
+            format_duration(simulator.seconds),
+            instruction.step,
+            instruction.name
+        );
+    }
+
+    println!(
+        "{count} of {} steps in {}",
+        instructions.len(),
+        format_duration(simulator.seconds)
+    );
+
+    Ok(())
+}
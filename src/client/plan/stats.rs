@@ -0,0 +1,246 @@
+use std::fmt::Write;
+
+use crate::{
+    config::PlannerConfig,
+    instructions::{control::Control, load_instructions, Action, Instruction},
+};
+
+use super::value;
+
+const ACTIONS: [Action; 4] = [Action::Walk, Action::Fly, Action::RightClick, Action::Mine];
+
+/// Movement from one destination to the next, driven by the action of the step
+/// it leads to. The first step has no leg since the start position is unknown.
+pub struct Leg {
+    pub step: usize,
+    pub action: Action,
+    pub hand_slot: char,
+    pub horizontal: f64,
+    pub vertical: f64,
+}
+
+impl Leg {
+    /// Expected seconds to drive this leg at the configured speeds
+    pub fn seconds(&self, speeds: &PlannerConfig) -> f64 {
+        match self.action {
+            Action::Walk if self.horizontal > speeds.sprint_distance => {
+                self.horizontal / speeds.sprint_speed
+            }
+            Action::Walk => self.horizontal / speeds.walk_speed,
+            // placing or breaking blocks, never sprinted
+            Action::RightClick | Action::Mine => self.horizontal / speeds.walk_speed,
+            // horizontal and vertical flight run side by side
+            Action::Fly => f64::max(
+                self.horizontal / speeds.fly_speed,
+                self.vertical.abs() / speeds.vertical_speed,
+            ),
+        }
+    }
+}
+
+pub fn legs(instructions: &[Instruction]) -> Vec<Leg> {
+    instructions
+        .windows(2)
+        .map(|pair| {
+            let (from, to) = (pair[0].destination, &pair[1]);
+
+            Leg {
+                step: to.step,
+                action: to.action,
+                hand_slot: to.hand_slot,
+                horizontal: f64::hypot(to.destination[0] - from[0], to.destination[2] - from[2]),
+                vertical: to.destination[1] - from[1],
+            }
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct ActionTotals {
+    steps: usize,
+    legs: usize,
+    horizontal: f64,
+    vertical: f64,
+    seconds: f64,
+}
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut args = args.iter();
+    let path = args.next().ok_or_else(|| "missing plan file".to_owned())?;
+    let mut speeds = PlannerConfig::load()?;
+
+    while let Some(arg) = args.next() {
+        let speed = match arg.as_str() {
+            "--walk-speed" => &mut speeds.walk_speed,
+            "--sprint-speed" => &mut speeds.sprint_speed,
+            "--fly-speed" => &mut speeds.fly_speed,
+            "--vertical-speed" => &mut speeds.vertical_speed,
+            _ => return Err(format!("unexpected argument {arg}")),
+        };
+
+        *speed = value(&mut args, arg)?
+            .parse::<f64>()
+            .ok()
+            .filter(|speed| *speed > 0.0)
+            .ok_or_else(|| format!("{arg} must be a positive number"))?;
+    }
+
+    let instructions = load_instructions(path)?;
+    print!("{}", summary(path, &instructions, &speeds));
+
+    Ok(())
+}
+
+fn slot(action: Action) -> usize {
+    match action {
+        Action::Walk => 0,
+        Action::Fly => 1,
+        Action::RightClick => 2,
+        Action::Mine => 3,
+    }
+}
+
+/// Seconds as `1h 02m 03s`, `4m 05s` or `12.3s`
+pub fn format_duration(seconds: f64) -> String {
+    let whole = seconds.round() as u64;
+
+    match whole {
+        0..=59 => format!("{seconds:.1}s"),
+        60..=3599 => format!("{}m {:02}s", whole / 60, whole % 60),
+        _ => format!(
+            "{}h {:02}m {:02}s",
+            whole / 3600,
+            whole / 60 % 60,
+            whole % 60
+        ),
+    }
+}
+
+fn summary(path: &str, instructions: &[Instruction], speeds: &PlannerConfig) -> String {
+    let mut text = String::new();
+    let legs = legs(instructions);
+    let mut totals: [ActionTotals; 4] = Default::default();
+
+    let mut waiting = 0.0;
+    let mut waits = 0;
+    let mut branches = 0;
+
+    for instruction in instructions {
+        match &instruction.control {
+            None => totals[slot(instruction.action)].steps += 1,
+            Some(Control::If { .. }) => branches += 1,
+            Some(control) => {
+                waiting += control
+                    .duration()
+                    .map_or(0.0, |duration| duration.as_secs_f64());
+                waits += 1;
+            }
+        }
+    }
+
+    for leg in &legs {
+        let totals = &mut totals[slot(leg.action)];
+
+        totals.legs += 1;
+        totals.horizontal += leg.horizontal;
+        totals.vertical += leg.vertical.abs();
+        totals.seconds += leg.seconds(speeds);
+    }
+
+    let horizontal: f64 = legs.iter().map(|leg| leg.horizontal).sum();
+    let climbed: f64 = legs.iter().map(|leg| leg.vertical.max(0.0)).sum();
+    let descended: f64 = legs.iter().map(|leg| (-leg.vertical).max(0.0)).sum();
+    let seconds: f64 = totals.iter().map(|totals| totals.seconds).sum::<f64>() + waiting;
+    let placing = &totals[slot(Action::RightClick)];
+
+    let _ = writeln!(text, "Plan {path}, {} steps", instructions.len());
+    let _ = writeln!(text);
+    let _ = writeln!(
+        text,
+        "{:<15} {:>5} {:>11} {:>9} {:>11}",
+        "action", "steps", "horizontal", "vertical", "duration"
+    );
+
+    for action in ACTIONS {
+        let totals = &totals[slot(action)];
+
+        let _ = writeln!(
+            text,
+            "{:<15} {:>5} {:>11.1} {:>9.1} {:>11}",
+            action.name(),
+            totals.steps,
+            totals.horizontal,
+            totals.vertical,
+            format_duration(totals.seconds)
+        );
+    }
+
+    let _ = writeln!(text);
+    let _ = writeln!(
+        text,
+        "Travel      {horizontal:.1} blocks horizontally, {climbed:.1} up, {descended:.1} down"
+    );
+    let _ = writeln!(
+        text,
+        "Placing     {} right-click legs over {:.1} blocks",
+        placing.legs, placing.horizontal
+    );
+
+    if waits > 0 {
+        let _ = writeln!(
+            text,
+            "Waiting     {} over {waits} steps that stay in place, wait_until counted as none",
+            format_duration(waiting)
+        );
+    }
+
+    if branches > 0 {
+        let _ = writeln!(
+            text,
+            "Branches    {branches} if steps, both of their branches counted"
+        );
+    }
+
+    if let Some(first) = instructions.first() {
+        let mut min = first.destination;
+        let mut max = first.destination;
+
+        for instruction in instructions {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(instruction.destination[axis]);
+                max[axis] = max[axis].max(instruction.destination[axis]);
+            }
+        }
+
+        let _ = writeln!(
+            text,
+            "Bounds      x {} to {}, y {} to {}, z {} to {} ({:.1} x {:.1} x {:.1} blocks)",
+            min[0],
+            max[0],
+            min[1],
+            max[1],
+            min[2],
+            max[2],
+            max[0] - min[0],
+            max[1] - min[1],
+            max[2] - min[2]
+        );
+    }
+
+    let _ = writeln!(
+        text,
+        "Duration    about {} from the first destination",
+        format_duration(seconds)
+    );
+    let _ = writeln!(
+        text,
+        "Speeds      walk {}, sprint {} past {} blocks, fly {}, vertical {} blocks/s",
+        speeds.walk_speed,
+        speeds.sprint_speed,
+        speeds.sprint_distance,
+        speeds.fly_speed,
+        speeds.vertical_speed
+    );
+
+    text
+}
@@ -0,0 +1,43 @@
+use std::fs;
+
+use serde_json::Value;
+
+/// Positions the player went through, read from a relay recording
+/// (`server --record`) or from a file of raw telemetry frames, one per line.
+/// Lines without coordinates, like commands or status frames, are skipped.
+pub fn read(path: &str) -> Result<Vec<[f64; 3]>, String> {
+    let source =
+        fs::read_to_string(path).map_err(|error| format!("could not read {path}: {error}"))?;
+
+    let mut positions = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let frame: Value =
+            serde_json::from_str(line).map_err(|error| format!("{path}:{}: {error}", index + 1))?;
+
+        // recorded frames carry the relayed text in `message`
+        let frame = match frame.get("message").and_then(Value::as_str) {
+            Some(message) => match serde_json::from_str(message) {
+                Ok(frame) => frame,
+                Err(_) => continue,
+            },
+            None => frame,
+        };
+
+        let coords = &frame["coords"];
+
+        if let (Some(x), Some(y), Some(z)) = (
+            coords["x"].as_f64(),
+            coords["y"].as_f64(),
+            coords["z"].as_f64(),
+        ) {
+            positions.push([x, y, z]);
+        }
+    }
+
+    Ok(positions)
+}
@@ -0,0 +1,57 @@
+use std::fs;
+
+use crate::instructions::{load_instructions, transform::Transform};
+
+use super::{position, value, waypoints::instructions_file};
+
+/// Applies the transforms given as flags, in their order, and writes the plan
+/// with absolute coordinates
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut args = args.iter();
+    let path = args.next().ok_or_else(|| "missing plan file".to_owned())?;
+
+    let mut transforms = Vec::new();
+    let mut pivot = None;
+    let mut output = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--translate" => {
+                let offset = position(value(&mut args, arg)?, arg)?;
+                transforms.push(Transform::Translate(offset));
+            }
+            "--rotate" => {
+                let degrees = value(&mut args, arg)?
+                    .parse::<f64>()
+                    .map_err(|_| "--rotate expects degrees".to_owned())?;
+
+                transforms.push(Transform::rotate(degrees)?);
+            }
+            "--mirror" => transforms.push(Transform::mirror(value(&mut args, arg)?)?),
+            "--reverse" => transforms.push(Transform::Reverse),
+            "--pivot" => pivot = Some(position(value(&mut args, arg)?, arg)?),
+            "--output" => output = Some(value(&mut args, arg)?.to_owned()),
+            _ => return Err(format!("unexpected argument {arg}")),
+        }
+    }
+
+    let mut instructions = load_instructions(path)?;
+
+    if let Some(pivot) = pivot.or(instructions.first().map(|first| first.destination)) {
+        for transform in &transforms {
+            transform.apply(&mut instructions, pivot)?;
+        }
+    }
+
+    let plan = instructions_file(&instructions);
+
+    match output {
+        Some(output) => {
+            fs::write(&output, plan).map_err(|error| format!("could not write {output}: {error}"))
+        }
+        None => {
+            print!("{plan}");
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::{fmt::Write, fs};
+
+use toml::Value;
+
+use crate::instructions::{control::Control, Action, Instruction, Looking};
+
+/// A destination about to be written to a plan file
+pub struct Waypoint {
+    pub action: Action,
+    pub destination: [f64; 3],
+    pub looking: Looking,
+    /// Script function driving the step, the destination being where it is expected to end
+    pub script: Option<String>,
+    /// Wait, hold, wait_until or if written in place of the destination
+    pub control: Option<Control>,
+}
+
+impl From<&Instruction> for Waypoint {
+    fn from(instruction: &Instruction) -> Self {
+        Self {
+            action: instruction.action,
+            destination: instruction.destination,
+            looking: instruction.looking,
+            script: instruction
+                .script
+                .as_ref()
+                .map(|call| call.function.clone()),
+            control: instruction.control.clone(),
+        }
+    }
+}
+
+/// Centre of the block a position is in
+pub fn snap(position: [f64; 3]) -> [f64; 3] {
+    [
+        position[0].floor() + 0.5,
+        position[1].floor(),
+        position[2].floor() + 0.5,
+    ]
+}
+
+/// Position rounded to a hundredth of a block, enough for a plan file
+pub fn round(position: [f64; 3]) -> [f64; 3] {
+    position.map(|coordinate| (coordinate * 100.0).round() / 100.0)
+}
+
+/// One instruction block holding the waypoints, in the layout of instructions.toml
+pub fn plan_file(name: &str, hand_slot: char, waypoints: &[Waypoint]) -> String {
+    let mut plan = String::new();
+    write_block(&mut plan, name, hand_slot, waypoints);
+
+    plan
+}
+
+/// Parsed instructions written back, a block for each run of steps sharing a
+/// name and a hand slot
+pub fn instructions_file(instructions: &[Instruction]) -> String {
+    let mut plan = String::new();
+    let mut rest = instructions;
+
+    let scripts = instructions
+        .iter()
+        .find_map(|instruction| instruction.script.as_ref())
+        .map(|call| &call.scripts.files);
+
+    // absolute, as the written plan may end up in another directory
+    if let Some(scripts) = scripts {
+        let scripts: Vec<String> = scripts
+            .iter()
+            .map(|file| {
+                fs::canonicalize(file)
+                    .map_or_else(|_| file.clone(), |path| path.display().to_string())
+            })
+            .collect();
+
+        let scripts = Value::Array(scripts.into_iter().map(Value::String).collect());
+        let _ = writeln!(plan, "scripts = {scripts}");
+    }
+
+    while let Some(first) = rest.first() {
+        let mut length = 0;
+
+        // an if and its branches stay in the block it starts in
+        while rest.get(length).is_some_and(|instruction| {
+            instruction.name == first.name && instruction.hand_slot == first.hand_slot
+        }) {
+            length += entry_length(&rest[length..]);
+        }
+
+        let waypoints: Vec<Waypoint> = rest[..length].iter().map(Waypoint::from).collect();
+
+        if !plan.is_empty() {
+            let _ = writeln!(plan);
+        }
+
+        write_block(&mut plan, &first.name, first.hand_slot, &waypoints);
+        rest = &rest[length..];
+    }
+
+    plan
+}
+
+/// Steps the entry starting the instructions spans, an if counting the steps
+/// of its branches, which the loader always puts right after it
+fn entry_length(instructions: &[Instruction]) -> usize {
+    match &instructions[0].control {
+        Some(Control::If {
+            then_steps,
+            else_steps,
+            ..
+        }) => 1 + then_steps + else_steps,
+        _ => 1,
+    }
+}
+
+/// A TOML string, quoted and escaped
+fn string(text: &str) -> Value {
+    Value::String(text.to_owned())
+}
+
+fn write_block(plan: &mut String, name: &str, hand_slot: char, waypoints: &[Waypoint]) {
+    let _ = writeln!(plan, "[[instruction]]");
+    let _ = writeln!(plan, "name = {}", string(name));
+    let _ = writeln!(plan, "hand_slot = \"{hand_slot}\"");
+    let _ = writeln!(plan, "path = [");
+    write_entries(plan, waypoints, 2);
+    let _ = writeln!(plan, "]");
+}
+
+/// Path entries at an indent, an if writing the steps of its branches within it
+fn write_entries(plan: &mut String, waypoints: &[Waypoint], indent: usize) {
+    let pad = " ".repeat(indent);
+    let mut rest = waypoints;
+
+    while let Some((waypoint, after)) = rest.split_first() {
+        rest = after;
+
+        let entry = match &waypoint.control {
+            Some(Control::Wait(duration)) => {
+                format!("{{ wait = {} }}", duration.as_secs_f64() * 1000.0)
+            }
+            Some(Control::Hold { input, duration }) => format!(
+                "{{ hold = {}, ms = {} }}",
+                string(input),
+                duration.as_secs_f64() * 1000.0
+            ),
+            Some(Control::WaitUntil { condition, timeout }) => format!(
+                "{{ wait_until = {}, timeout = {} }}",
+                string(&condition.to_string()),
+                timeout.as_secs_f64() * 1000.0
+            ),
+            Some(Control::Click(input)) => {
+                let button = input.trim_end_matches("_click");
+                format!("{{ click = {} }}", string(button))
+            }
+            Some(Control::Drop { stack }) => match stack {
+                true => "{ drop = \"stack\" }".to_owned(),
+                false => "{ drop = \"item\" }".to_owned(),
+            },
+            Some(Control::SwapHands) => "{ swap_hands = true }".to_owned(),
+            Some(Control::Select(slot)) => format!("{{ select = {slot} }}"),
+            Some(Control::Chat(text)) => format!("{{ chat = {} }}", string(text)),
+            Some(Control::If {
+                condition,
+                then_steps,
+                else_steps,
+            }) => {
+                let (then, after) = rest.split_at(*then_steps);
+                let (otherwise, after) = after.split_at(*else_steps);
+                rest = after;
+
+                let condition = string(&condition.to_string());
+                let _ = writeln!(plan, "{pad}{{ if = {condition}, then = [");
+                write_entries(plan, then, indent + 2);
+                let _ = writeln!(plan, "{pad}], else = [");
+                write_entries(plan, otherwise, indent + 2);
+                let _ = writeln!(plan, "{pad}] }},");
+                continue;
+            }
+            None => step_entry(waypoint),
+        };
+
+        let _ = writeln!(plan, "{pad}{entry},");
+    }
+}
+
+fn step_entry(waypoint: &Waypoint) -> String {
+    let [x, y, z] = waypoint.destination;
+    let action = format!("\"{}\",", waypoint.action.name());
+
+    let looking = match waypoint.looking {
+        Looking::Front => String::new(),
+        Looking::Back => ", looking = \"back\"".to_owned(),
+        Looking::Direction(direction) => format!(", looking = {direction:?}"),
+    };
+
+    let script = match &waypoint.script {
+        Some(function) => format!(", script = {}", string(function)),
+        None => String::new(),
+    };
+
+    format!("{{ action = {action:<17} destination = [{x:?}, {y:?}, {z:?}]{looking}{script} }}")
+}
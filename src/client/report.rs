@@ -0,0 +1,465 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use futures_util::lock::Mutex;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::instructions::Instruction;
+
+pub const REPORTS_DIRECTORY: &str = "reports";
+
+/// How long the distance to the goal may stay without real progress before it counts as stuck
+const STUCK_AFTER: Duration = Duration::from_secs(5);
+/// Blocks the distance has to shrink by to count as progress
+const STUCK_PROGRESS: f64 = 0.5;
+/// Age of the last telemetry frame after which the bot is steering blind
+const STALE_TELEMETRY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepOutcome {
+    Reached,
+    Skipped,
+    Interrupted,
+}
+
+impl StepOutcome {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StepOutcome::Reached => "reached",
+            StepOutcome::Skipped => "skipped",
+            StepOutcome::Interrupted => "interrupted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOutcome {
+    Completed,
+    Aborted,
+    /// Another plan was started before this one finished
+    Replaced,
+    Timeout,
+}
+
+impl RunOutcome {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RunOutcome::Completed => "completed",
+            RunOutcome::Aborted => "aborted",
+            RunOutcome::Replaced => "replaced",
+            RunOutcome::Timeout => "timeout",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IncidentKind {
+    Stuck { distance: f64 },
+    StaleTelemetry { age_seconds: f64 },
+    Reconnect { attempts: u32 },
+    Script { error: String },
+    Condition { error: String },
+    HandSlot { slot: char, held: Option<char> },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Incident {
+    /// Seconds since the run started
+    pub at: f64,
+    pub step: Option<usize>,
+    #[serde(flatten)]
+    pub kind: IncidentKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepReport {
+    pub step: usize,
+    pub name: String,
+    pub action: &'static str,
+    /// Seconds since the run started
+    pub started_at: f64,
+    pub ended_at: Option<f64>,
+    pub duration_seconds: Option<f64>,
+    /// Distance to the destination when the step ended
+    pub position_error: Option<f64>,
+    pub head_corrections: u64,
+    pub flight_pulses: u64,
+    pub outcome: Option<StepOutcome>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Totals {
+    pub duration_seconds: f64,
+    pub steps: usize,
+    pub steps_reached: usize,
+    pub steps_skipped: usize,
+    pub mean_position_error: Option<f64>,
+    pub max_position_error: Option<f64>,
+    pub head_corrections: u64,
+    pub flight_pulses: u64,
+    pub incidents: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunReport {
+    /// Plan file, `None` for the plan built into the binary
+    pub plan: Option<String>,
+    pub plan_length: usize,
+    pub started_at_ms: u64,
+    pub finished_at_ms: Option<u64>,
+    pub outcome: Option<RunOutcome>,
+    pub steps: Vec<StepReport>,
+    pub incidents: Vec<Incident>,
+    pub totals: Totals,
+}
+
+/// Progress of the current step used to detect it being stuck
+#[derive(Debug)]
+struct Progress {
+    best_distance: f64,
+    best_at: Instant,
+    last_distance: Option<f64>,
+    flight_pulses_at_start: u64,
+    is_stuck: bool,
+}
+
+/// Collects what happens during a run, shared by the instructions task and the websocket loop
+#[derive(Debug)]
+pub struct Report {
+    run: Option<RunReport>,
+    started: Instant,
+    progress: Option<Progress>,
+    is_telemetry_stale: bool,
+}
+
+impl Report {
+    pub fn new_shared() -> Arc<Mutex<Report>> {
+        Arc::new(Mutex::new(Self {
+            run: None,
+            started: Instant::now(),
+            progress: None,
+            is_telemetry_stale: false,
+        }))
+    }
+
+    fn elapsed(&self) -> f64 {
+        self.started.elapsed().as_secs_f64()
+    }
+
+    pub fn begin(&mut self, plan: Option<String>, plan_length: usize) {
+        self.started = Instant::now();
+        self.progress = None;
+        self.is_telemetry_stale = false;
+
+        self.run = Some(RunReport {
+            plan,
+            plan_length,
+            started_at_ms: unix_ms(SystemTime::now()),
+            finished_at_ms: None,
+            outcome: None,
+            steps: Vec::new(),
+            incidents: Vec::new(),
+            totals: Totals::default(),
+        });
+    }
+
+    /// Whether the previous step ended and the next tick starts a new one
+    pub fn is_between_steps(&self) -> bool {
+        self.run.is_some() && self.progress.is_none()
+    }
+
+    pub fn step_started(&mut self, instruction: &Instruction, flight_pulses: u64) {
+        let started_at = self.elapsed();
+
+        let Some(run) = &mut self.run else {
+            return;
+        };
+
+        run.steps.push(StepReport {
+            step: instruction.step,
+            name: instruction.name.clone(),
+            action: instruction.kind(),
+            started_at,
+            ended_at: None,
+            duration_seconds: None,
+            position_error: None,
+            head_corrections: 0,
+            flight_pulses: 0,
+            outcome: None,
+        });
+
+        self.progress = Some(Progress {
+            best_distance: f64::INFINITY,
+            best_at: Instant::now(),
+            last_distance: None,
+            flight_pulses_at_start: flight_pulses,
+            is_stuck: false,
+        });
+    }
+
+    /// Records one tick of the current step, flagging it once when it stops making progress
+    pub fn tick(&mut self, distance: f64, head_corrected: bool) {
+        let at = self.elapsed();
+
+        let (Some(run), Some(progress)) = (&mut self.run, &mut self.progress) else {
+            return;
+        };
+
+        let Some(step) = run.steps.last_mut() else {
+            return;
+        };
+
+        if head_corrected {
+            step.head_corrections += 1;
+        }
+
+        progress.last_distance = Some(distance);
+
+        if distance < progress.best_distance - STUCK_PROGRESS {
+            progress.best_distance = distance;
+            progress.best_at = Instant::now();
+            progress.is_stuck = false;
+        } else if !progress.is_stuck && progress.best_at.elapsed() > STUCK_AFTER {
+            progress.is_stuck = true;
+
+            run.incidents.push(Incident {
+                at,
+                step: Some(step.step),
+                kind: IncidentKind::Stuck { distance },
+            });
+        }
+    }
+
+    /// Time spent paused does not count towards being stuck
+    pub fn resumed(&mut self) {
+        if let Some(progress) = &mut self.progress {
+            progress.best_at = Instant::now();
+        }
+    }
+
+    /// Flags telemetry that stopped arriving, once until it is fresh again
+    pub fn telemetry_age(&mut self, age: Duration) {
+        let is_stale = age > STALE_TELEMETRY;
+
+        if is_stale && !self.is_telemetry_stale {
+            self.incident(IncidentKind::StaleTelemetry {
+                age_seconds: age.as_secs_f64(),
+            });
+        }
+
+        self.is_telemetry_stale = is_stale;
+    }
+
+    pub fn incident(&mut self, kind: IncidentKind) {
+        let at = self.elapsed();
+
+        if let Some(run) = &mut self.run {
+            let step = run.steps.last().filter(|step| step.outcome.is_none());
+
+            run.incidents.push(Incident {
+                at,
+                step: step.map(|step| step.step),
+                kind,
+            });
+        }
+    }
+
+    pub fn step_ended(&mut self, outcome: StepOutcome, flight_pulses: u64) {
+        let ended_at = self.elapsed();
+        let progress = self.progress.take();
+
+        let Some(step) = self.run.as_mut().and_then(|run| run.steps.last_mut()) else {
+            return;
+        };
+
+        if step.outcome.is_some() {
+            return;
+        }
+
+        step.ended_at = Some(ended_at);
+        step.duration_seconds = Some(ended_at - step.started_at);
+        step.outcome = Some(outcome);
+
+        if let Some(progress) = progress {
+            step.position_error = progress.last_distance;
+            step.flight_pulses = flight_pulses.saturating_sub(progress.flight_pulses_at_start);
+        }
+    }
+
+    /// Closes the run, returning its report when one was running
+    pub fn finish(&mut self, outcome: RunOutcome, flight_pulses: u64) -> Option<RunReport> {
+        let step_outcome = match outcome {
+            RunOutcome::Completed => StepOutcome::Reached,
+            _ => StepOutcome::Interrupted,
+        };
+
+        self.step_ended(step_outcome, flight_pulses);
+
+        let duration_seconds = self.elapsed();
+        let mut run = self.run.take()?;
+
+        run.finished_at_ms = Some(unix_ms(SystemTime::now()));
+        run.outcome = Some(outcome);
+        run.totals = totals(&run, duration_seconds);
+
+        Some(run)
+    }
+}
+
+fn totals(run: &RunReport, duration_seconds: f64) -> Totals {
+    let errors: Vec<f64> = run
+        .steps
+        .iter()
+        .filter_map(|step| step.position_error)
+        .collect();
+
+    let count = |outcome: fn(&StepOutcome) -> bool| {
+        run.steps
+            .iter()
+            .filter(|step| step.outcome.as_ref().is_some_and(outcome))
+            .count()
+    };
+
+    Totals {
+        duration_seconds,
+        steps: run.steps.len(),
+        steps_reached: count(|outcome| matches!(outcome, StepOutcome::Reached)),
+        steps_skipped: count(|outcome| matches!(outcome, StepOutcome::Skipped)),
+        mean_position_error: (!errors.is_empty())
+            .then(|| errors.iter().sum::<f64>() / errors.len() as f64),
+        max_position_error: errors.iter().copied().reduce(f64::max),
+        head_corrections: run.steps.iter().map(|step| step.head_corrections).sum(),
+        flight_pulses: run.steps.iter().map(|step| step.flight_pulses).sum(),
+        incidents: run.incidents.len(),
+    }
+}
+
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn format_optional(value: Option<f64>, suffix: &str) -> String {
+    value.map_or("-".to_owned(), |value| format!("{value:.2}{suffix}"))
+}
+
+/// Human readable version of the report, written next to the JSON one
+pub fn summary(run: &RunReport) -> String {
+    let mut text = String::new();
+    let totals = &run.totals;
+
+    let outcome = run.outcome.map_or("unfinished", |outcome| outcome.name());
+
+    let _ = writeln!(
+        text,
+        "Run {outcome} after {:.1}s, plan {}",
+        totals.duration_seconds,
+        run.plan.as_deref().unwrap_or("built-in")
+    );
+    let _ = writeln!(
+        text,
+        "{}/{} steps reached, {} skipped",
+        totals.steps_reached, run.plan_length, totals.steps_skipped
+    );
+    let _ = writeln!(
+        text,
+        "Position error mean {} max {}",
+        format_optional(totals.mean_position_error, ""),
+        format_optional(totals.max_position_error, "")
+    );
+    let _ = writeln!(
+        text,
+        "{} head corrections, {} flight pulses, {} incidents",
+        totals.head_corrections, totals.flight_pulses, totals.incidents
+    );
+
+    let _ = writeln!(text);
+    let _ = writeln!(
+        text,
+        "{:>4}  {:<20} {:<15} {:>9} {:>7} {:>11} {:>6}  outcome",
+        "step", "name", "action", "duration", "error", "corrections", "pulses"
+    );
+
+    for step in &run.steps {
+        let outcome = step.outcome.map_or("-", |outcome| outcome.name());
+
+        let _ = writeln!(
+            text,
+            "{:>4}  {:<20} {:<15} {:>9} {:>7} {:>11} {:>6}  {outcome}",
+            step.step,
+            step.name,
+            step.action,
+            format_optional(step.duration_seconds, "s"),
+            format_optional(step.position_error, ""),
+            step.head_corrections,
+            step.flight_pulses
+        );
+    }
+
+    if !run.incidents.is_empty() {
+        let _ = writeln!(text);
+        let _ = writeln!(text, "Incidents");
+
+        for incident in &run.incidents {
+            let step = incident
+                .step
+                .map_or("between steps".to_owned(), |step| format!("step {step}"));
+
+            let description = match &incident.kind {
+                IncidentKind::Stuck { distance } => {
+                    format!("stuck {distance:.2} away from the destination")
+                }
+                IncidentKind::StaleTelemetry { age_seconds } => {
+                    format!("no telemetry for {age_seconds:.1}s")
+                }
+                IncidentKind::Reconnect { attempts } => {
+                    format!("websocket reconnected after {attempts} attempts")
+                }
+                IncidentKind::Script { error } => format!("script failed: {error}"),
+                IncidentKind::Condition { error } => format!("condition failed: {error}"),
+                IncidentKind::HandSlot { slot, held } => match held {
+                    Some(held) => format!("hand slot {slot} never selected, {held} still held"),
+                    None => format!("hand slot {slot} never selected"),
+                },
+            };
+
+            let _ = writeln!(text, "  {:>7.1}s  {step}: {description}", incident.at);
+        }
+    }
+
+    text
+}
+
+/// Closes the run if one is going on and writes its report to [`REPORTS_DIRECTORY`]
+pub async fn close(report: &Mutex<Report>, outcome: RunOutcome, flight_pulses: u64) {
+    let Some(run) = report.lock().await.finish(outcome, flight_pulses) else {
+        return;
+    };
+
+    match write(&run, Path::new(REPORTS_DIRECTORY)) {
+        Ok(path) => info!(path = %path.display(), outcome = outcome.name(), "run report written"),
+        Err(error) => warn!(%error, "could not write the run report"),
+    }
+}
+
+/// Writes `run-<start>.json` and `run-<start>.txt` into `directory`
+pub fn write(run: &RunReport, directory: &Path) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(directory)?;
+
+    let path = directory.join(format!("run-{}.json", run.started_at_ms));
+
+    fs::write(&path, serde_json::to_string_pretty(run).unwrap())?;
+    fs::write(path.with_extension("txt"), summary(run))?;
+
+    Ok(path)
+}
@@ -0,0 +1,391 @@
+use std::{fmt, fs, path::PathBuf};
+
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST, INT};
+use tracing::{debug, info};
+
+use crate::{
+    instructions::{Action, Looking},
+    minecraft_resource::MinecraftResource,
+};
+
+/// Operations a script call may run before it is stopped, so a runaway loop
+/// cannot hold up a tick
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// What a script step asks for on this tick
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Directive {
+    /// Head for a position the way a plain step would
+    Move {
+        action: Action,
+        destination: [f64; 3],
+        looking: Looking,
+    },
+    /// Turn the head to a yaw without moving
+    Look(f64),
+    /// Stay still and ask again on the next tick
+    Wait,
+    /// The step is over
+    Done,
+}
+
+/// The script files of a plan, compiled together so their functions can call
+/// each other
+pub struct Scripts {
+    engine: Engine,
+    ast: AST,
+    pub files: Vec<String>,
+}
+
+impl fmt::Debug for Scripts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scripts")
+            .field("files", &self.files)
+            .finish()
+    }
+}
+
+fn number(value: &Dynamic) -> Option<f64> {
+    value
+        .as_float()
+        .ok()
+        .or_else(|| value.as_int().ok().map(|integer| integer as f64))
+}
+
+fn step(action: Action, x: Dynamic, y: Dynamic, z: Dynamic) -> Result<Map, Box<EvalAltResult>> {
+    let mut destination = Vec::new();
+
+    for coordinate in [x, y, z] {
+        let value = number(&coordinate)
+            .ok_or_else(|| format!("expected a number, got {}", coordinate.type_name()))?;
+
+        destination.push(Dynamic::from_float(value));
+    }
+
+    let mut step = Map::new();
+    step.insert("action".into(), action.name().into());
+    step.insert("destination".into(), destination.into());
+
+    Ok(step)
+}
+
+fn flag(name: &str) -> Map {
+    let mut map = Map::new();
+    map.insert(name.into(), true.into());
+    map
+}
+
+/// Engine with the movement primitives, each returning the map a script
+/// function hands back to say what to do:
+/// `walk_to(x, y, z)`, `fly_to(x, y, z)`, `click_at(x, y, z)`,
+/// `mine_to(x, y, z)`, `look(yaw)`, `wait()` and `done()`
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    engine.on_print(|text| info!(target: "script", "{text}"));
+    engine.on_debug(|text, _, position| debug!(target: "script", %position, "{text}"));
+
+    engine.register_fn("walk_to", |x, y, z| step(Action::Walk, x, y, z));
+    engine.register_fn("fly_to", |x, y, z| step(Action::Fly, x, y, z));
+    engine.register_fn("click_at", |x, y, z| step(Action::RightClick, x, y, z));
+    engine.register_fn("mine_to", |x, y, z| step(Action::Mine, x, y, z));
+    engine.register_fn("wait", || flag("wait"));
+    engine.register_fn("done", || flag("done"));
+
+    engine.register_fn("look", |yaw: Dynamic| -> Result<Map, Box<EvalAltResult>> {
+        let yaw = number(&yaw).ok_or_else(|| format!("expected a yaw, got {}", yaw.type_name()))?;
+
+        let mut map = Map::new();
+        map.insert("look".into(), Dynamic::from_float(yaw));
+        Ok(map)
+    });
+
+    engine
+}
+
+impl Scripts {
+    pub fn load(files: &[PathBuf]) -> Result<Self, String> {
+        let engine = engine();
+        let mut ast = AST::empty();
+
+        for path in files {
+            let file = path.display().to_string();
+            let source = fs::read_to_string(path)
+                .map_err(|error| format!("could not read {file}: {error}"))?;
+
+            let compiled = engine
+                .compile(source)
+                .map_err(|error| format!("{file}: {error}"))?;
+
+            ast += compiled;
+        }
+
+        Ok(Self {
+            engine,
+            ast,
+            files: files
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect(),
+        })
+    }
+
+    /// Whether a step can call `function`, which takes the player as its one
+    /// parameter
+    pub fn has_step(&self, function: &str) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|metadata| metadata.name == function && metadata.params.len() == 1)
+    }
+
+    /// Calls `fn <function>(player)`, `player` holding `x`, `y`, `z`, `yaw`,
+    /// `pitch` and the `ticks` the step has been running. `this` is a map the
+    /// step keeps from one call to the next.
+    pub fn call(
+        &self,
+        function: &str,
+        resource: &MinecraftResource,
+        ticks: u64,
+        memory: &mut Dynamic,
+    ) -> Result<Directive, String> {
+        let position = resource.player_position;
+
+        let mut player = Map::new();
+        player.insert("x".into(), Dynamic::from_float(position.x));
+        player.insert("y".into(), Dynamic::from_float(position.y));
+        player.insert("z".into(), Dynamic::from_float(position.z));
+        player.insert("yaw".into(), Dynamic::from_float(resource.player_head.yaw));
+        player.insert("pitch".into(), Dynamic::from_float(resource.player_head.y));
+        player.insert("ticks".into(), Dynamic::from_int(ticks as INT));
+
+        let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(memory);
+
+        let result = self
+            .engine
+            .call_fn_with_options::<Dynamic>(
+                options,
+                &mut Scope::new(),
+                &self.ast,
+                function,
+                (player,),
+            )
+            .map_err(|error| format!("{function}: {error}"))?;
+
+        directive(result).map_err(|error| format!("{function}: {error}"))
+    }
+}
+
+/// Reads what a script function returned, nothing meaning the step is over
+fn directive(result: Dynamic) -> Result<Directive, String> {
+    if result.is_unit() {
+        return Ok(Directive::Done);
+    }
+
+    let type_name = result.type_name();
+    let map = result
+        .try_cast::<Map>()
+        .ok_or_else(|| format!("expected a step, got {type_name}"))?;
+
+    let is_set = |key: &str| map.get(key).and_then(|value| value.as_bool().ok()) == Some(true);
+
+    if is_set("done") {
+        return Ok(Directive::Done);
+    }
+
+    if is_set("wait") {
+        return Ok(Directive::Wait);
+    }
+
+    if let Some(yaw) = map.get("look") {
+        return number(yaw)
+            .map(Directive::Look)
+            .ok_or_else(|| "look expects a yaw".to_owned());
+    }
+
+    let action = match map.get("action") {
+        Some(action) => {
+            let name = action.clone().into_string().unwrap_or_default();
+            Action::parse(&name).ok_or_else(|| format!("unknown action {name:?}"))?
+        }
+        None => Action::Walk,
+    };
+
+    let destination = map
+        .get("destination")
+        .and_then(|destination| destination.clone().into_array().ok())
+        .map(|coords| coords.iter().filter_map(number).collect::<Vec<f64>>())
+        .and_then(|coords| <[f64; 3]>::try_from(coords).ok())
+        .ok_or_else(|| "a step needs destination = [x, y, z]".to_owned())?;
+
+    let looking = match map.get("looking") {
+        None => Looking::Front,
+        Some(looking) => match number(looking) {
+            Some(yaw) => Looking::Direction(yaw),
+            None if looking.clone().into_string().as_deref() == Ok("back") => Looking::Back,
+            None => return Err(format!("looking expects a yaw or \"back\", got {looking}")),
+        },
+    };
+
+    Ok(Directive::Move {
+        action,
+        destination,
+        looking,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::minecraft_resource::{PlayerHead, PlayerPosition};
+
+    fn scripts(name: &str, source: &str) -> Scripts {
+        let path = env::temp_dir().join(format!("acecraft-{}-{name}.rhai", process::id()));
+        fs::write(&path, source).unwrap();
+
+        let scripts = Scripts::load(std::slice::from_ref(&path));
+        let _ = fs::remove_file(path);
+
+        scripts.unwrap()
+    }
+
+    fn resource(x: f64, y: f64, z: f64) -> MinecraftResource {
+        MinecraftResource {
+            player_position: PlayerPosition::from((x, y, z)),
+            player_head: PlayerHead { yaw: 0.0, y: 0.0 },
+            updated_at: None,
+            telemetry: Value::Null,
+        }
+    }
+
+    fn call_once(scripts: &Scripts, function: &str) -> Result<Directive, String> {
+        let mut memory = Dynamic::from_map(Map::new());
+        scripts.call(function, &resource(0.0, 0.0, 0.0), 0, &mut memory)
+    }
+
+    /// Calls the function tick after tick until it is done, the player
+    /// reaching every destination and turning to every yaw at once
+    fn simulate(
+        scripts: &Scripts,
+        function: &str,
+        resource: &mut MinecraftResource,
+    ) -> Vec<Directive> {
+        let mut memory = Dynamic::from_map(Map::new());
+        let mut directives = Vec::new();
+
+        for ticks in 0..100 {
+            let directive = scripts
+                .call(function, resource, ticks, &mut memory)
+                .unwrap();
+            directives.push(directive);
+
+            match directive {
+                Directive::Move {
+                    destination: [x, y, z],
+                    ..
+                } => {
+                    resource.player_position = PlayerPosition::from((x, y, z));
+                }
+                Directive::Look(yaw) => resource.player_head.yaw = yaw,
+                Directive::Wait => (),
+                Directive::Done => return directives,
+            }
+        }
+
+        panic!("{function} never finished: {directives:?}");
+    }
+
+    #[test]
+    fn runs_a_script_step_to_its_end() {
+        let scripts = scripts(
+            "row",
+            r#"
+            fn row(player) {
+                if player.ticks < 2 { return wait(); }
+                if this.target == () { this.target = player.x + 2.0; }
+
+                if player.x < this.target {
+                    return walk_to(player.x + 1.0, player.y, player.z);
+                }
+
+                if this.mined != true {
+                    this.mined = true;
+                    return mine_to(player.x, player.y - 1, player.z);
+                }
+
+                if player.yaw != 90.0 { return look(90); }
+                done()
+            }
+            "#,
+        );
+
+        assert!(scripts.has_step("row"));
+
+        let mut resource = resource(0.5, 64.0, 0.5);
+        let directives = simulate(&scripts, "row", &mut resource);
+
+        let walk = |x| Directive::Move {
+            action: Action::Walk,
+            destination: [x, 64.0, 0.5],
+            looking: Looking::Front,
+        };
+
+        assert_eq!(
+            directives,
+            [
+                Directive::Wait,
+                Directive::Wait,
+                walk(1.5),
+                walk(2.5),
+                Directive::Move {
+                    action: Action::Mine,
+                    destination: [2.5, 63.0, 0.5],
+                    looking: Looking::Front,
+                },
+                Directive::Look(90.0),
+                Directive::Done,
+            ]
+        );
+
+        let position = resource.player_position;
+        assert_eq!([position.x, position.y, position.z], [2.5, 63.0, 0.5]);
+        assert_eq!(resource.player_head.yaw, 90.0);
+    }
+
+    #[test]
+    fn rejects_a_return_value_that_is_not_a_step() {
+        let scripts = scripts("number", "fn number(player) { 42 }");
+        let error = call_once(&scripts, "number").unwrap_err();
+
+        assert!(error.contains("expected a step"), "{error}");
+    }
+
+    #[test]
+    fn rejects_an_unknown_action() {
+        let scripts = scripts(
+            "jump",
+            r#"fn jump(player) { #{ action: "jump", destination: [0, 0, 0] } }"#,
+        );
+        let error = call_once(&scripts, "jump").unwrap_err();
+
+        assert!(error.contains("unknown action \"jump\""), "{error}");
+    }
+
+    #[test]
+    fn stops_a_runaway_loop() {
+        let scripts = scripts(
+            "spin",
+            "fn spin(player) { let turns = 0; loop { turns += 1; } }",
+        );
+        let error = call_once(&scripts, "spin").unwrap_err();
+
+        assert!(
+            error.to_lowercase().contains("too many operations"),
+            "{error}"
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use futures_util::lock::Mutex;
+use serde::Serialize;
+
+use crate::instructions::Instruction;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunState {
+    Running,
+    Paused,
+    Idle,
+}
+
+/// Snapshot of the run published to the relay as `{ "type": "status", ... }`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename = "status")]
+pub struct Status {
+    pub state: RunState,
+    /// Zero based index of the current step within the plan
+    pub step_index: usize,
+    pub step_count: usize,
+    pub step_name: Option<String>,
+    pub action: Option<&'static str>,
+    pub remaining_steps: usize,
+    pub distance_to_goal: Option<f64>,
+    /// Remaining path length divided by the speed observed so far
+    pub eta_seconds: Option<f64>,
+    pub inputs: Vec<&'static str>,
+    /// Last command failure, cleared by the next successful command
+    pub error: Option<String>,
+}
+
+impl Status {
+    pub fn new_shared() -> Arc<Mutex<Status>> {
+        Arc::new(Mutex::new(Self {
+            state: RunState::Idle,
+            step_index: 0,
+            step_count: 0,
+            step_name: None,
+            action: None,
+            remaining_steps: 0,
+            distance_to_goal: None,
+            eta_seconds: None,
+            inputs: Vec::new(),
+            error: None,
+        }))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlanStep {
+    pub name: String,
+    pub action: &'static str,
+    pub destination: [f64; 3],
+}
+
+/// Steps of the plan being run, published as `{ "type": "plan", ... }` whenever it changes
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename = "plan")]
+pub struct Plan {
+    pub steps: Vec<PlanStep>,
+}
+
+impl Plan {
+    pub fn new<'a>(instructions: impl Iterator<Item = &'a Instruction>) -> Self {
+        let steps = instructions
+            .map(|instruction| PlanStep {
+                name: instruction.name.clone(),
+                action: instruction.kind(),
+                destination: instruction.destination,
+            })
+            .collect();
+
+        Self { steps }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
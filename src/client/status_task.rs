@@ -0,0 +1,30 @@
+use std::{sync::Arc, time::Duration};
+
+use futures_util::lock::Mutex;
+use tokio::{sync::mpsc::UnboundedSender, time::sleep};
+
+use crate::{ace_state::AceState, status::Status};
+
+const STATUS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Publishes the run status, with the inputs currently held, through the websocket
+pub fn spawn(
+    status: Arc<Mutex<Status>>,
+    ace_state: Arc<Mutex<AceState>>,
+    outgoing: UnboundedSender<String>,
+) {
+    tokio::spawn(async move {
+        loop {
+            sleep(STATUS_INTERVAL).await;
+
+            let inputs = ace_state.lock().await.active_inputs();
+
+            let mut status = status.lock().await;
+            status.inputs = inputs;
+
+            if outgoing.send(status.to_json()).is_err() {
+                break;
+            }
+        }
+    });
+}
@@ -0,0 +1,262 @@
+use std::{fs, thread};
+
+use actix_web::web::Bytes;
+use actix_web_actors::ws;
+use futures_util::{SinkExt, StreamExt as _};
+use rdev::{listen, EventType, Key};
+use serde_json::Value;
+use tokio::{select, sync::mpsc};
+use tracing::{info, warn};
+
+use crate::{
+    config::ClientConfig,
+    instructions::{load_instructions, Action, Looking},
+    plan::waypoints::{plan_file, round, snap, Waypoint},
+};
+
+pub const USAGE: &str = "usage:
+  client teach <plan.toml> [--snap] [--name <name>] [--hand-slot <slot>]
+
+F6 records the current position, F7 cycles the action and F8 the looking of
+the next waypoints, F9 drops the last waypoint and F10 saves and quits";
+
+const RECORD_KEY: Key = Key::F6;
+const ACTION_KEY: Key = Key::F7;
+const LOOKING_KEY: Key = Key::F8;
+const UNDO_KEY: Key = Key::F9;
+const SAVE_KEY: Key = Key::F10;
+
+const ACTIONS: [Action; 4] = [Action::Walk, Action::Fly, Action::RightClick, Action::Mine];
+
+#[derive(Debug, Clone, Copy)]
+enum Hotkey {
+    Record,
+    Action,
+    Looking,
+    Undo,
+    Save,
+}
+
+struct Options {
+    output: String,
+    snap: bool,
+    name: String,
+    hand_slot: char,
+}
+
+/// What is being taught: the recorded waypoints and the tags for the next ones
+struct Lesson {
+    options: Options,
+    waypoints: Vec<Waypoint>,
+    action: usize,
+    looking: Looking,
+    position: Option<[f64; 3]>,
+    yaw: f64,
+}
+
+impl Lesson {
+    fn handle(&mut self, hotkey: Hotkey) -> Result<(), String> {
+        match hotkey {
+            Hotkey::Record => {
+                let Some(position) = self.position else {
+                    warn!("no telemetry yet, waypoint not recorded");
+                    return Ok(());
+                };
+
+                let destination = match self.options.snap {
+                    true => snap(position),
+                    false => round(position),
+                };
+
+                self.waypoints.push(Waypoint {
+                    action: ACTIONS[self.action],
+                    destination,
+                    looking: self.looking,
+                    script: None,
+                    control: None,
+                });
+
+                info!(
+                    step = self.waypoints.len(),
+                    ?destination,
+                    action = ACTIONS[self.action].name(),
+                    "waypoint recorded"
+                );
+
+                self.save()?;
+            }
+
+            Hotkey::Action => {
+                self.action = (self.action + 1) % ACTIONS.len();
+                info!(
+                    action = ACTIONS[self.action].name(),
+                    "next waypoints tagged"
+                );
+            }
+
+            Hotkey::Looking => {
+                self.looking = match self.looking {
+                    Looking::Front => Looking::Back,
+                    Looking::Back => Looking::Direction(self.yaw.round()),
+                    Looking::Direction(_) => Looking::Front,
+                };
+
+                info!(looking = ?self.looking, "next waypoints tagged");
+            }
+
+            Hotkey::Undo => {
+                if let Some(waypoint) = self.waypoints.pop() {
+                    info!(destination = ?waypoint.destination, "waypoint dropped");
+                    self.save()?;
+                }
+            }
+
+            Hotkey::Save => {
+                self.save()?;
+                load_instructions(&self.options.output)?;
+
+                info!(
+                    path = %self.options.output,
+                    waypoints = self.waypoints.len(),
+                    "plan saved"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let plan = plan_file(&self.options.name, self.options.hand_slot, &self.waypoints);
+
+        fs::write(&self.options.output, plan)
+            .map_err(|error| format!("could not write {}: {error}", self.options.output))
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut args = args.iter();
+
+    let mut options = Options {
+        output: args
+            .next()
+            .ok_or_else(|| "missing plan file to write".to_owned())?
+            .to_owned(),
+        snap: false,
+        name: "Taught".to_owned(),
+        hand_slot: '1',
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for {arg}"))
+        };
+
+        match arg.as_str() {
+            "--snap" => options.snap = true,
+            "--name" => options.name = value()?.to_owned(),
+            "--hand-slot" => {
+                options.hand_slot = value()?
+                    .parse::<char>()
+                    .map_err(|_| "--hand-slot expects a single character".to_owned())?
+            }
+            _ => return Err(format!("unexpected argument {arg}")),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Listens for the hotkeys globally, since the game window has the focus
+fn spawn_hotkeys(hotkeys: mpsc::UnboundedSender<Hotkey>) {
+    thread::spawn(move || {
+        let result = listen(move |event| {
+            let hotkey = match event.event_type {
+                EventType::KeyPress(RECORD_KEY) => Hotkey::Record,
+                EventType::KeyPress(ACTION_KEY) => Hotkey::Action,
+                EventType::KeyPress(LOOKING_KEY) => Hotkey::Looking,
+                EventType::KeyPress(UNDO_KEY) => Hotkey::Undo,
+                EventType::KeyPress(SAVE_KEY) => Hotkey::Save,
+                _ => return,
+            };
+
+            let _ = hotkeys.send(hotkey);
+        });
+
+        if let Err(error) = result {
+            warn!(?error, "hotkeys unavailable");
+        }
+    });
+}
+
+/// Records waypoints from telemetry while the player moves by hand
+pub async fn run(args: &[String]) -> Result<(), String> {
+    let options = parse_options(args)?;
+    let config = ClientConfig::load();
+
+    let (_, mut ws) = config
+        .websocket_request()
+        .connect()
+        .await
+        .map_err(|error| format!("could not connect to {}: {error}", config.websocket_url))?;
+
+    let (hotkeys_tx, mut hotkeys) = mpsc::unbounded_channel();
+    spawn_hotkeys(hotkeys_tx);
+
+    info!(
+        path = %options.output,
+        "teaching, {RECORD_KEY:?} records, {ACTION_KEY:?} action, {LOOKING_KEY:?} looking, {UNDO_KEY:?} undo, {SAVE_KEY:?} save"
+    );
+
+    let mut lesson = Lesson {
+        options,
+        waypoints: Vec::new(),
+        action: 0,
+        looking: Looking::Front,
+        position: None,
+        yaw: 0.0,
+    };
+
+    loop {
+        select! {
+          websocket_message = ws.next() => {
+            match websocket_message {
+              Some(Ok(ws::Frame::Ping(_))) => {
+                let _ = ws.send(ws::Message::Pong(Bytes::new())).await;
+              },
+
+              Some(Ok(ws::Frame::Text(text_received))) => {
+                let Ok(output) = serde_json::from_slice::<Value>(&text_received) else {
+                  continue;
+                };
+
+                if let (Some(x), Some(y), Some(z)) = (
+                  output["coords"]["x"].as_f64(),
+                  output["coords"]["y"].as_f64(),
+                  output["coords"]["z"].as_f64(),
+                ) {
+                  lesson.position = Some([x, y, z]);
+                  lesson.yaw = output["head"]["yaw"].as_f64().unwrap_or(lesson.yaw);
+                }
+              },
+
+              Some(Ok(ws::Frame::Close(_))) | Some(Err(_)) | None => {
+                lesson.save()?;
+                return Err("websocket closed, waypoints so far were saved".to_owned());
+              },
+
+              _ => ()
+            }
+          },
+
+          Some(hotkey) = hotkeys.recv() => {
+            lesson.handle(hotkey)?;
+
+            if let Hotkey::Save = hotkey {
+              return Ok(());
+            }
+          }
+        }
+    }
+}
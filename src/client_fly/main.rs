@@ -1,10 +1,18 @@
 mod ace_state;
 mod actions;
+// the planner settings are only read by the modular client
+#[path = "../client/config.rs"]
+#[allow(dead_code)]
+mod config;
 mod instructions;
+mod logging;
 mod minecraft_resource;
+#[path = "../url_query.rs"]
+mod url_query;
 mod vectors;
 
 use std::{
+    env,
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -12,6 +20,7 @@ use std::{
 use ace_state::AceState;
 use actix_web::web::Bytes;
 use awc::ws;
+use config::ClientConfig;
 use enigo::*;
 use futures_util::{lock::Mutex, SinkExt, StreamExt as _};
 use instructions::{list_instructions, Instruction, Looking};
@@ -22,6 +31,7 @@ use tokio::{
     sync::broadcast,
     time::{sleep, timeout},
 };
+use tracing::{debug, info, info_span, Span};
 
 use crate::{
     minecraft_resource::PlayerHead,
@@ -57,13 +67,12 @@ enum Message {
 
 #[actix_web::main]
 async fn main() {
+    logging::init(env::args().any(|arg| arg == "--log-json"));
+
     let (tx, _) = broadcast::channel::<Message>(100);
 
-    let (_, mut ws) = awc::Client::new()
-        .ws(WEBSOCKET_URL)
-        .connect()
-        .await
-        .unwrap();
+    let config = ClientConfig::load();
+    let (_, mut ws) = config.websocket_request().connect().await.unwrap();
 
     let ace_state = AceState::new_shared();
     let enigo = Arc::new(Mutex::new(Enigo::new()));
@@ -91,7 +100,7 @@ async fn main() {
 
                     Message::Hand { instruction } => {
                         if instruction.reset_hand_stack && !ace_state.hand_stack_reseted {
-                            println!("Reseting Hand Stack");
+                            debug!(instruction = %instruction.name, "resetting hand stack");
                             enigo.key_click(Key::E);
                             tokio::time::sleep(TICKRATE_DURATION * 2).await;
 
@@ -130,7 +139,7 @@ async fn main() {
                                 let is_fresh = elapsed >= TICKRATE_DURATION * 10;
 
                                 if is_fresh {
-                                    println!("UP {distance}");
+                                    debug!(distance, "flying up");
 
                                     tokio::task::spawn(async move {
                                         let mut enigo = Enigo::new();
@@ -152,7 +161,7 @@ async fn main() {
                                 let is_fresh = elapsed >= TICKRATE_DURATION * 10;
 
                                 if is_fresh {
-                                    println!("DOWN {distance}");
+                                    debug!(distance, "flying down");
                                     enigo.key_click(Key::Shift);
                                     ace_state.last_shift_press = Instant::now();
                                 }
@@ -218,7 +227,7 @@ async fn main() {
                     Message::InstructionFinished { instruction } => {
                         ace_state.hand_stack_reseted = false;
 
-                        println!("Releasing All (Instruction Finished)");
+                        debug!(instruction = %instruction.name, "releasing all keys");
                         if ace_state.is_pressing_right_click {
                             enigo.mouse_up(MouseButton::Right);
                             ace_state.is_pressing_right_click = false;
@@ -246,9 +255,24 @@ async fn main() {
 
         tokio::spawn(async move {
             let mut instructions = instructions.lock().await;
+            let plan_length = instructions.len();
+            let run_span = info_span!("run", plan_length);
+            let mut span = Span::none();
+            let mut current_step = None;
+
             while let Some(instruction) = instructions.last().cloned() {
                 sleep(TICKRATE_DURATION).await;
 
+                let step = plan_length - instructions.len();
+
+                if current_step != Some(step) {
+                    span = info_span!(parent: &run_span, "instruction", step, name = %instruction.name);
+                    span.in_scope(
+                        || info!(destination = ?instruction.destination, "instruction started"),
+                    );
+                    current_step = Some(step);
+                }
+
                 let minecraft_resource = main_minecraft_resource.lock().await;
 
                 let my_position = Vector3D::from(minecraft_resource.player_position);
@@ -285,6 +309,16 @@ async fn main() {
 
                 let distance = my_position.distance_to(&goal_position);
 
+                span.in_scope(|| {
+                    debug!(
+                        distance,
+                        horizontal_force,
+                        vertical_force,
+                        position = ?minecraft_resource.player_position,
+                        "tick"
+                    )
+                });
+
                 tx.send(Message::Hand {
                     instruction: instruction.clone(),
                 })
@@ -305,6 +339,7 @@ async fn main() {
                 .unwrap();
 
                 if distance < DISTANCE_TO_FINISH_INSTRUCTION {
+                    span.in_scope(|| info!(distance, "instruction reached"));
                     instructions.pop();
                     let tx = tx.clone();
                     tokio::task::spawn(async move {
@@ -316,6 +351,8 @@ async fn main() {
                     });
                 }
             }
+
+            run_span.in_scope(|| info!("plan finished"));
         });
 
         let ws_minecraft_resource = minecraft_resource.clone();
@@ -326,20 +363,31 @@ async fn main() {
                 match websocket_message {
                   Ok(ws::Frame::Ping(_)) => ws.send(ws::Message::Pong(Bytes::new())).await.unwrap(),
                   Ok(ws::Frame::Text(text_received)) => {
-                    let output: Value = serde_json::from_slice(&text_received).unwrap();
-
-                    let mut minecraft_resource = ws_minecraft_resource.lock().await;
+                    let Ok(output) = serde_json::from_slice::<Value>(&text_received) else {
+                      continue;
+                    };
 
-                    minecraft_resource.player_position = PlayerPosition::from((
-                      output["coords"]["x"].as_f64().unwrap(),
-                      output["coords"]["y"].as_f64().unwrap(),
-                      output["coords"]["z"].as_f64().unwrap(),
-                    ));
+                    // status, plan and ack frames of the bot share the room
+                    if output.get("type").is_some() {
+                      continue;
+                    }
 
-                    minecraft_resource.player_head = PlayerHead {
-                      yaw: output["head"]["yaw"].as_f64().unwrap(),
-                      y: output["head"]["y"].as_f64().unwrap(),
-                    };
+                    if let (Some(x), Some(y), Some(z)) = (
+                      output["coords"]["x"].as_f64(),
+                      output["coords"]["y"].as_f64(),
+                      output["coords"]["z"].as_f64(),
+                    ) {
+                      let mut minecraft_resource = ws_minecraft_resource.lock().await;
+
+                      minecraft_resource.player_position = PlayerPosition::from((x, y, z));
+
+                      if let (Some(yaw), Some(head_y)) = (
+                        output["head"]["yaw"].as_f64(),
+                        output["head"]["y"].as_f64(),
+                      ) {
+                        minecraft_resource.player_head = PlayerHead { yaw, y: head_y };
+                      }
+                    }
                   },
 
                   _ => ()
@@ -354,6 +402,6 @@ async fn main() {
     };
 
     if let Err(_) = timeout(TIMEOUT_DURATION, main_task).await {
-        println!("Terminated after timeout.");
+        info!("terminated after timeout");
     }
 }
@@ -3,6 +3,8 @@ pub mod try_action {
 
     use enigo::*;
 
+    use tracing::debug;
+
     use crate::ace_state::AceState;
 
     pub mod stop {
@@ -10,11 +12,13 @@ pub mod try_action {
 
         use enigo::*;
 
+        use tracing::debug;
+
         use crate::ace_state::AceState;
 
         pub fn walk(ace_state: &mut AceState, enigo: &mut Enigo) {
             if ace_state.is_walking {
-                println!("Releasing W");
+                debug!(key = "W", "releasing");
                 enigo.key_up(Key::W);
                 ace_state.is_walking = false;
             }
@@ -22,7 +26,7 @@ pub mod try_action {
 
         pub fn sneak(ace_state: &mut AceState, enigo: &mut Enigo) {
             if ace_state.is_sneaking {
-                println!("Releasing Shift");
+                debug!(key = "Shift", "releasing");
                 enigo.key_up(Key::Shift);
                 ace_state.is_sneaking = false;
             }
@@ -30,9 +34,9 @@ pub mod try_action {
 
         pub async fn run(ace_state: &mut AceState, enigo: &mut Enigo, tickrate_duration: Duration) {
             if ace_state.is_running {
-                println!("Releasing Control");
+                debug!(key = "Control", "releasing");
                 enigo.key_up(Key::Control);
-                println!("Repressing W");
+                debug!(key = "W", "repressing");
                 enigo.key_up(Key::W);
                 tokio::time::sleep(tickrate_duration).await;
                 enigo.key_down(Key::W);
@@ -44,7 +48,7 @@ pub mod try_action {
 
     pub fn walk(ace_state: &mut AceState, enigo: &mut Enigo) {
         if !ace_state.is_walking {
-            println!("Pressing W");
+            debug!(key = "W", "pressing");
             enigo.key_down(Key::W);
             ace_state.is_walking = true;
         }
@@ -52,7 +56,7 @@ pub mod try_action {
 
     pub fn sneak(ace_state: &mut AceState, enigo: &mut Enigo) {
         if !ace_state.is_sneaking {
-            println!("Pressing Shift");
+            debug!(key = "Shift", "pressing");
             enigo.key_down(Key::Shift);
             ace_state.is_sneaking = true;
         };
@@ -60,7 +64,7 @@ pub mod try_action {
 
     pub fn run(ace_state: &mut AceState, enigo: &mut Enigo) {
         if !ace_state.is_running {
-            println!("Pressing Control");
+            debug!(key = "Control", "pressing");
             enigo.key_down(Key::Control);
             ace_state.is_running = true;
         };
@@ -72,7 +76,7 @@ pub mod try_action {
         tickrate_duration: Duration,
     ) {
         if !ace_state.hand_stack_reseted {
-            println!("Reseting Hand Stack");
+            debug!("resetting hand stack");
             enigo.key_click(Key::E);
             tokio::time::sleep(tickrate_duration * 2).await;
 
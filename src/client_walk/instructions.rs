@@ -1,6 +1,7 @@
 use std::fmt::Display;
 
 use toml::{map::Map, Value};
+use tracing::debug;
 
 #[derive(Debug, Clone)]
 pub struct Walk {
@@ -34,6 +35,19 @@ pub struct Instruction {
     pub repeat_right_click: bool,
     pub change_hand_slot_to: char,
     pub reset_hand_stack: bool,
+    /// Come to a standstill here even when following the path
+    pub stop: bool,
+}
+
+impl Instruction {
+    /// Whether the player has to stand still here before `next` starts, because
+    /// the hand does something else or the inventory is opened
+    pub fn stops_before(&self, next: &Instruction) -> bool {
+        self.stop
+            || self.repeat_right_click != next.repeat_right_click
+            || self.change_hand_slot_to != next.change_hand_slot_to
+            || next.reset_hand_stack
+    }
 }
 
 impl Display for Instruction {
@@ -105,6 +119,13 @@ fn get_change_hand_slot_to(table: &Map<String, Value>, default: Option<char>) ->
         .unwrap_or(default.unwrap_or('1'))
 }
 
+fn get_stop(table: &Map<String, Value>) -> bool {
+    table
+        .get("stop")
+        .and_then(|item| item.as_bool())
+        .unwrap_or(false)
+}
+
 fn get_reverse(table: &Map<String, Value>, default: Option<bool>) -> bool {
     table
         .get("reverse")
@@ -194,6 +215,7 @@ pub fn list_instructions() -> Vec<Instruction> {
                 repeat_right_click,
                 name: name.to_string(),
                 walk: Walk::from(coords),
+                stop: false,
             };
 
             let mut instructions = table
@@ -209,13 +231,14 @@ pub fn list_instructions() -> Vec<Instruction> {
                         let coords = get_to(table);
 
                         let mut instruction = coords_to_instruction(coords);
+                        instruction.stop = get_stop(table);
 
                         instruction.repeat_right_click = (action == "right_clicking" && !reverse)
                             || (action == "walking" && reverse);
                         instruction.reset_hand_stack = (action == "right_clicking" && !reverse)
                             || (action == "walking" && reverse);
 
-                        println!("{instruction}");
+                        debug!("{instruction}");
 
                         instruction
                     })
@@ -0,0 +1,52 @@
+use std::{fs::OpenOptions, sync::Mutex};
+
+use tracing_subscriber::{
+    fmt::{self, writer::BoxMakeWriter},
+    prelude::*,
+    EnvFilter,
+};
+
+use crate::tui::WarningsLayer;
+
+/// Filter used when `RUST_LOG` is not set. Levels can be raised per module,
+/// e.g. `RUST_LOG=info,client_walk::actions=debug`
+const DEFAULT_FILTER: &str = "info";
+
+/// Where the log goes while the full screen UI owns the terminal
+const TUI_LOG_PATH: &str = "client_walk.log";
+
+/// Installs the global subscriber, writing one JSON object per event when `json` is set.
+/// With the full screen UI the log is appended to a file and warnings feed its panel
+pub fn init(json: bool, tui: bool) {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+
+    let writer = match tui {
+        true => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(TUI_LOG_PATH)
+                .unwrap_or_else(|error| panic!("could not open {TUI_LOG_PATH}: {error}"));
+
+            BoxMakeWriter::new(Mutex::new(file))
+        }
+        false => BoxMakeWriter::new(std::io::stdout),
+    };
+
+    let format = match json {
+        true => fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_writer(writer)
+            .boxed(),
+        false => fmt::layer().with_ansi(!tui).with_writer(writer).boxed(),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(format)
+        .with(tui.then_some(WarningsLayer))
+        .init();
+}
@@ -1,15 +1,29 @@
 mod ace_state;
 mod actions;
+// the planner settings are only read by the modular client
+#[path = "../client/config.rs"]
+#[allow(dead_code)]
+mod config;
 mod instructions;
+mod logging;
 mod minecraft_resource;
+mod pursuit;
+mod tui;
+#[path = "../url_query.rs"]
+mod url_query;
 mod vectors;
 
-use std::{sync::Arc, time::Duration};
+use std::{
+    env, process,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use ace_state::AceState;
 use actions::try_action;
 use actix_web::web::Bytes;
 use awc::ws;
+use config::ClientConfig;
 use enigo::*;
 use futures_util::{lock::Mutex, SinkExt, StreamExt as _};
 use instructions::{list_instructions, Instruction};
@@ -20,6 +34,8 @@ use tokio::{
     sync::broadcast,
     time::{sleep, timeout},
 };
+use tracing::{debug, info, info_span, warn, Span};
+use tui::{Controls, View};
 
 use crate::{
     minecraft_resource::PlayerHead,
@@ -33,16 +49,23 @@ const WEBSOCKET_URL: &str = "ws://127.0.0.1:8080/ws";
 const SNEAK_END_AT: f64 = 3.0;
 const WALK_START_AT: f64 = 0.2;
 const RUN_START_AT: f64 = 5.0;
+/// While following the path, `rotate_before_walk` only stops for turns sharper than this
+const SHARP_TURN_DEGREES: f64 = 60.0;
+const USAGE: &str = "usage:
+  client_walk [--pursuit | --lookahead <blocks>] [--tui] [--log-json]";
 
 #[derive(Debug, Clone)]
 enum Message {
     InstructionFinished,
+    /// A leg ended at a corner taken on the move, the walk keys staying down
+    CornerTaken,
     HeadMovement {
         horizontal_force: i32,
         vertical_force: i32,
     },
     Walk {
         distance: f64,
+        yaw_error: f64,
         instruction: Instruction,
     },
     Hand {
@@ -50,15 +73,49 @@ enum Message {
     },
 }
 
+/// Lookahead in blocks when the path is followed with `--pursuit` or `--lookahead <blocks>`
+fn lookahead() -> Option<f64> {
+    let mut args = env::args().skip(1);
+    let mut lookahead = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pursuit" => lookahead = lookahead.or(Some(pursuit::DEFAULT_LOOKAHEAD)),
+            "--lookahead" => {
+                let blocks = args
+                    .next()
+                    .and_then(|blocks| blocks.parse::<f64>().ok())
+                    .filter(|blocks| *blocks > 0.0)
+                    .unwrap_or_else(|| {
+                        exit_with_usage("--lookahead expects a positive number of blocks")
+                    });
+
+                lookahead = Some(blocks);
+            }
+            _ => (),
+        }
+    }
+
+    lookahead
+}
+
+fn exit_with_usage(reason: &str) -> ! {
+    eprintln!("{reason}\n{USAGE}");
+    process::exit(2);
+}
+
 #[actix_web::main]
 async fn main() {
+    let is_tui = env::args().any(|arg| arg == "--tui");
+    logging::init(env::args().any(|arg| arg == "--log-json"), is_tui);
+
+    let lookahead = lookahead();
+    let is_pursuing = lookahead.is_some();
+
     let (tx, _) = broadcast::channel::<Message>(100);
 
-    let (_, mut ws) = awc::Client::new()
-        .ws(WEBSOCKET_URL)
-        .connect()
-        .await
-        .unwrap();
+    let config = ClientConfig::load();
+    let (_, mut ws) = config.websocket_request().connect().await.unwrap();
 
     let ace_state = Arc::new(Mutex::new(AceState::new()));
     let enigo = Arc::new(Mutex::new(Enigo::new()));
@@ -66,6 +123,15 @@ async fn main() {
 
     let mut instructions = list_instructions();
     instructions.reverse();
+
+    let view = View::new_shared(instructions.len());
+    let controls = Controls::new_shared();
+
+    if is_tui {
+        tui::spawn(view.clone(), ace_state.clone(), controls.clone());
+        tui::spawn_hotkeys(controls.clone());
+    }
+
     let instructions = Arc::new(Mutex::new(instructions));
 
     let mut foot_receiver = tx.subscribe();
@@ -77,6 +143,10 @@ async fn main() {
 
     let foot_ace_state = ace_state.clone();
 
+    let instructions_view = view.clone();
+    let instructions_controls = controls.clone();
+    let abort_tx = tx.clone();
+
     let main_task = async move {
         // thread for foot movement
         tokio::spawn(async move {
@@ -96,9 +166,16 @@ async fn main() {
 
                     Message::Walk {
                         distance,
+                        yaw_error,
                         instruction,
                     } => {
-                        if instruction.rotate_before_walk && ace_state.is_turning {
+                        // while following the path only sharp corners are worth stopping for
+                        let must_rotate = match is_pursuing {
+                            true => yaw_error.abs() > SHARP_TURN_DEGREES,
+                            false => ace_state.is_turning,
+                        };
+
+                        if instruction.rotate_before_walk && must_rotate {
                             try_action::stop::walk(&mut ace_state, &mut enigo);
                             try_action::stop::sneak(&mut ace_state, &mut enigo);
                             try_action::stop::run(&mut ace_state, &mut enigo, TICKRATE_DURATION)
@@ -138,7 +215,7 @@ async fn main() {
 
                     Message::Hand { instruction } => {
                         if !ace_state.hand_slot_changed {
-                            println!("Changing Hand Slot");
+                            debug!(slot = %instruction.change_hand_slot_to, "changing hand slot");
                             enigo.key_click(Key::Layout(instruction.change_hand_slot_to));
                             ace_state.hand_slot_changed = true;
                         }
@@ -154,15 +231,20 @@ async fn main() {
 
                         if instruction.repeat_right_click {
                             if !ace_state.is_pressing_right_click {
-                                println!("Pressing Mouse Right Button");
+                                debug!(key = "Mouse Right", "pressing");
                                 enigo.mouse_down(MouseButton::Right);
                                 ace_state.is_pressing_right_click = true;
                             }
                         }
                     }
 
+                    Message::CornerTaken => {
+                        ace_state.hand_slot_changed = false;
+                        ace_state.hand_stack_reseted = false;
+                    }
+
                     Message::InstructionFinished => {
-                        println!("Releasing All (Instruction Finished)");
+                        debug!("releasing all keys");
                         ace_state.hand_slot_changed = false;
                         ace_state.hand_stack_reseted = false;
 
@@ -190,20 +272,70 @@ async fn main() {
 
         tokio::spawn(async move {
             let mut instructions = instructions.lock().await;
+            let mut was_paused = false;
+
+            let plan_length = instructions.len();
+            let run_span = info_span!("run", plan_length);
+            let mut span = Span::none();
+            let mut current_step = None;
+            let mut previous_destination: Option<[f64; 3]> = None;
+
             while let Some(instruction) = instructions.last().cloned() {
                 sleep(TICKRATE_DURATION).await;
 
+                let step = plan_length - instructions.len();
+
+                if current_step != Some(step) {
+                    span = info_span!(parent: &run_span, "instruction", step, name = %instruction.name);
+                    span.in_scope(|| info!(to = %instruction.walk, "instruction started"));
+                    current_step = Some(step);
+                }
+
+                if instructions_controls.is_aborted() {
+                    break;
+                }
+
+                if instructions_controls.is_paused() {
+                    if !was_paused {
+                        span.in_scope(|| info!("paused"));
+                        tx.send(Message::InstructionFinished).unwrap();
+                        was_paused = true;
+                    }
+
+                    continue;
+                }
+
+                was_paused = false;
+
                 let minecraft_resource = minecraft_resource2.lock().await;
 
                 let my_position = Vector3D::from(minecraft_resource.player_position);
                 let goal_position = Vector3D::from(instruction.walk.to);
+                let distance = my_position.distance_to(&goal_position);
 
-                let horizontal_force = my_position
-                    .horizontal_angle_distance_to(
-                        &goal_position,
-                        minecraft_resource.player_head.yaw,
-                    )
-                    .calculate_angle_force(32, -32);
+                // where to steer, how far the next standstill is and whether the corner is behind
+                let (steer_position, walk_distance, corner_passed) = match lookahead {
+                    Some(lookahead) => {
+                        let position = [my_position.x, my_position.y, my_position.z];
+                        let start = *previous_destination.get_or_insert(position);
+
+                        let path = pursuit::path(start, instructions.iter().rev());
+                        let progress = pursuit::progress(&position, &path);
+                        let target = pursuit::target(&progress, &path, lookahead);
+
+                        (Vector3D::from(target), progress.remaining, progress.leg > 0)
+                    }
+
+                    None => (Vector3D::from(instruction.walk.to), distance, false),
+                };
+
+                let yaw_error = my_position.horizontal_angle_distance_to(
+                    &steer_position,
+                    minecraft_resource.player_head.yaw,
+                );
+                // within -180..180 so it reads as the turn left to make
+                let yaw_error = (yaw_error + 180.0).rem_euclid(360.0) - 180.0;
+                let horizontal_force = yaw_error.calculate_angle_force(32, -32);
 
                 let vertical_angle = if instruction.look_downwards {
                     85.0
@@ -220,10 +352,26 @@ async fn main() {
                 })
                 .unwrap();
 
-                let distance = my_position.distance_to(&goal_position);
+                span.in_scope(|| {
+                    debug!(
+                        distance,
+                        yaw_error,
+                        horizontal_force,
+                        vertical_force,
+                        position = ?minecraft_resource.player_position,
+                        "tick"
+                    )
+                });
+
+                let mut view = instructions_view.lock().await;
+                view.instruction = Some(instruction.to_string());
+                view.distance = Some(distance);
+                view.yaw_error = Some(yaw_error);
+                drop(view);
 
                 tx.send(Message::Walk {
-                    distance,
+                    distance: walk_distance,
+                    yaw_error,
                     instruction: instruction.clone(),
                 })
                 .unwrap();
@@ -233,11 +381,29 @@ async fn main() {
                 })
                 .unwrap();
 
-                if distance < 0.1 {
+                if corner_passed {
+                    span.in_scope(|| info!(distance, "corner taken"));
+                    previous_destination = Some(instruction.walk.to);
+                    instructions.pop();
+                    tx.send(Message::CornerTaken).unwrap();
+
+                    let mut view = instructions_view.lock().await;
+                    view.remaining = instructions.len();
+                } else if distance < 0.1 {
+                    span.in_scope(|| info!(distance, "instruction reached"));
+                    previous_destination = Some(instruction.walk.to);
                     instructions.pop();
                     tx.send(Message::InstructionFinished).unwrap();
+
+                    let mut view = instructions_view.lock().await;
+                    view.remaining = instructions.len();
+                    view.instruction = None;
                 }
             }
+
+            if instructions.is_empty() {
+                run_span.in_scope(|| info!("plan finished"));
+            }
         });
 
         loop {
@@ -246,26 +412,46 @@ async fn main() {
                 match websocket_message {
                   Ok(ws::Frame::Ping(_)) => ws.send(ws::Message::Pong(Bytes::new())).await.unwrap(),
                   Ok(ws::Frame::Text(text_received)) => {
-                    let output: Value = serde_json::from_slice(&text_received).unwrap();
+                    let Ok(output) = serde_json::from_slice::<Value>(&text_received) else {
+                      continue;
+                    };
+
+                    // status, plan and ack frames of the bot share the room
+                    if output.get("type").is_some() {
+                      continue;
+                    }
 
-                    let mut minecraft_resource = minecraft_resource1.lock().await;
+                    if let (Some(x), Some(y), Some(z)) = (
+                      output["coords"]["x"].as_f64(),
+                      output["coords"]["y"].as_f64(),
+                      output["coords"]["z"].as_f64(),
+                    ) {
+                      let mut minecraft_resource = minecraft_resource1.lock().await;
 
-                    minecraft_resource.player_position = PlayerPosition::from((
-                      output["coords"]["x"].as_f64().unwrap(),
-                      output["coords"]["y"].as_f64().unwrap(),
-                      output["coords"]["z"].as_f64().unwrap(),
-                    ));
+                      minecraft_resource.player_position = PlayerPosition::from((x, y, z));
 
-                    minecraft_resource.player_head = PlayerHead {
-                      yaw: output["head"]["yaw"].as_f64().unwrap(),
-                      y: output["head"]["y"].as_f64().unwrap(),
-                    };
+                      if let (Some(yaw), Some(head_y)) = (
+                        output["head"]["yaw"].as_f64(),
+                        output["head"]["y"].as_f64(),
+                      ) {
+                        minecraft_resource.player_head = PlayerHead { yaw, y: head_y };
+                      }
+
+                      view.lock().await.telemetry_at = Some(Instant::now());
+                    }
                   },
 
                   _ => ()
                 }
               },
 
+              _ = controls.wait_abort() => {
+                warn!("aborted, releasing all keys");
+                abort_tx.send(Message::InstructionFinished).unwrap();
+                sleep(TICKRATE_DURATION * 5).await;
+                break;
+              },
+
               _ = sleep(TIMEOUT_SECONDS_DURATION) => {
                 break;
               }
@@ -273,7 +459,10 @@ async fn main() {
         }
     };
 
-    if let Err(_) = timeout(TIMEOUT_SECONDS_DURATION, main_task).await {
-        println!("Terminated after timeout.");
+    let result = timeout(TIMEOUT_SECONDS_DURATION, main_task).await;
+    tui::restore();
+
+    if let Err(_) = result {
+        info!("terminated after timeout");
     }
 }
@@ -0,0 +1,111 @@
+use crate::instructions::Instruction;
+
+/// Blocks ahead of the player the steering point is taken
+pub const DEFAULT_LOOKAHEAD: f64 = 1.5;
+
+/// Where the player is along the path
+pub struct Progress {
+    /// Leg the closest point is on, 0 being the leg toward the current destination
+    pub leg: usize,
+    pub point: [f64; 3],
+    /// Distance left along the path to the next standstill
+    pub remaining: f64,
+}
+
+fn horizontal_distance(from: &[f64; 3], to: &[f64; 3]) -> f64 {
+    f64::hypot(to[0] - from[0], to[2] - from[2])
+}
+
+fn lerp(from: &[f64; 3], to: &[f64; 3], along: f64) -> [f64; 3] {
+    [
+        from[0] + (to[0] - from[0]) * along,
+        from[1] + (to[1] - from[1]) * along,
+        from[2] + (to[2] - from[2]) * along,
+    ]
+}
+
+/// Closest point to `position` on the leg, as a fraction of the leg
+fn closest_along(position: &[f64; 3], from: &[f64; 3], to: &[f64; 3]) -> f64 {
+    let (dx, dz) = (to[0] - from[0], to[2] - from[2]);
+    let length = dx * dx + dz * dz;
+
+    match length > 0.0 {
+        true => {
+            (((position[0] - from[0]) * dx + (position[2] - from[2]) * dz) / length).clamp(0.0, 1.0)
+        }
+        false => 1.0,
+    }
+}
+
+/// The path to follow from `start`: the current destination and the ones after
+/// it, up to the first that needs a standstill. `upcoming` is in running order.
+pub fn path<'a>(
+    start: [f64; 3],
+    upcoming: impl IntoIterator<Item = &'a Instruction>,
+) -> Vec<[f64; 3]> {
+    let mut path = vec![start];
+    let mut previous: Option<&Instruction> = None;
+
+    for instruction in upcoming {
+        if let Some(previous) = previous {
+            if previous.stops_before(instruction) {
+                break;
+            }
+        }
+
+        path.push(instruction.walk.to);
+        previous = Some(instruction);
+    }
+
+    path
+}
+
+/// Only the current leg and the next are considered, so a path that doubles
+/// back on itself cannot pull the player ahead
+pub fn progress(position: &[f64; 3], path: &[[f64; 3]]) -> Progress {
+    let (leg, point) = path
+        .windows(2)
+        .take(2)
+        .enumerate()
+        .map(|(leg, pair)| {
+            let along = closest_along(position, &pair[0], &pair[1]);
+            (leg, lerp(&pair[0], &pair[1], along))
+        })
+        .min_by(|(_, a), (_, b)| {
+            horizontal_distance(position, a).total_cmp(&horizontal_distance(position, b))
+        })
+        .unwrap_or((0, *position));
+
+    let remaining = horizontal_distance(&point, &path[(leg + 1).min(path.len() - 1)])
+        + path
+            .windows(2)
+            .skip(leg + 1)
+            .map(|pair| horizontal_distance(&pair[0], &pair[1]))
+            .sum::<f64>();
+
+    Progress {
+        leg,
+        point,
+        remaining,
+    }
+}
+
+/// Point `lookahead` blocks further along the path, or its end. Steering toward
+/// it rather than the destination starts the turn before each corner.
+pub fn target(progress: &Progress, path: &[[f64; 3]], lookahead: f64) -> [f64; 3] {
+    let mut from = progress.point;
+    let mut left = lookahead;
+
+    for to in path.iter().skip(progress.leg + 1) {
+        let length = horizontal_distance(&from, to);
+
+        if length >= left {
+            return lerp(&from, to, left / length);
+        }
+
+        left -= length;
+        from = *to;
+    }
+
+    from
+}
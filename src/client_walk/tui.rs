@@ -0,0 +1,292 @@
+use std::{
+    collections::VecDeque,
+    fmt::{self, Write as _},
+    io::{stdout, Write},
+    panic, process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex as StdMutex, PoisonError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use futures_util::lock::Mutex;
+use rdev::{listen, EventType, Key};
+use tokio::{signal, sync::Notify, time::sleep};
+use tracing::{
+    field::{Field, Visit},
+    warn, Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, Layer};
+
+use crate::ace_state::AceState;
+
+const RENDER_INTERVAL: Duration = Duration::from_millis(200);
+const PROGRESS_WIDTH: usize = 40;
+const WARNINGS_KEPT: usize = 6;
+const STALE_TELEMETRY: Duration = Duration::from_secs(2);
+
+// F6 to F10 are the hotkeys of `client teach`, F1 to F3, F5 and F11 are taken
+// by the game
+const PAUSE_KEY: Key = Key::F4;
+const ABORT_KEY: Key = Key::F12;
+
+/// Whether the full screen UI owns the terminal
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static WARNINGS: StdMutex<VecDeque<String>> = StdMutex::new(VecDeque::new());
+
+/// Keeps warnings and errors for the warnings panel, since the log itself goes to a file
+pub struct WarningsLayer;
+
+impl<S: Subscriber> Layer<S> for WarningsLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if *event.metadata().level() > Level::WARN {
+            return;
+        }
+
+        let mut visitor = WarningVisitor::default();
+        event.record(&mut visitor);
+
+        let mut warnings = WARNINGS.lock().unwrap();
+        warnings.push_back(visitor.message + &visitor.fields);
+
+        while warnings.len() > WARNINGS_KEPT {
+            warnings.pop_front();
+        }
+    }
+}
+
+#[derive(Default)]
+struct WarningVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for WarningVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{value:?}"),
+            name => {
+                let _ = write!(self.fields, " {name}={value:?}");
+            }
+        }
+    }
+}
+
+/// What the instructions loop and the websocket loop report for the UI to draw
+#[derive(Debug)]
+pub struct View {
+    pub plan_length: usize,
+    pub remaining: usize,
+    pub instruction: Option<String>,
+    pub distance: Option<f64>,
+    pub yaw_error: Option<f64>,
+    pub telemetry_at: Option<Instant>,
+}
+
+impl View {
+    pub fn new_shared(plan_length: usize) -> Arc<Mutex<View>> {
+        Arc::new(Mutex::new(Self {
+            plan_length,
+            remaining: plan_length,
+            instruction: None,
+            distance: None,
+            yaw_error: None,
+            telemetry_at: None,
+        }))
+    }
+}
+
+/// Pause and abort requests, set by the hotkeys
+#[derive(Debug, Default)]
+pub struct Controls {
+    paused: AtomicBool,
+    aborted: AtomicBool,
+    abort_requested: Notify,
+}
+
+impl Controls {
+    pub fn new_shared() -> Arc<Controls> {
+        Arc::new(Self::default())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::Relaxed)
+    }
+
+    /// Resolves once the abort hotkey was pressed
+    pub async fn wait_abort(&self) {
+        self.abort_requested.notified().await
+    }
+
+    fn toggle_pause(&self) {
+        self.paused.fetch_xor(true, Ordering::Relaxed);
+    }
+
+    fn abort(&self) {
+        self.aborted.store(true, Ordering::Relaxed);
+        self.abort_requested.notify_one();
+    }
+}
+
+/// Listens for the hotkeys globally, since the game window has the focus
+pub fn spawn_hotkeys(controls: Arc<Controls>) {
+    thread::spawn(move || {
+        let result = listen(move |event| match event.event_type {
+            EventType::KeyPress(PAUSE_KEY) => controls.toggle_pause(),
+            EventType::KeyPress(ABORT_KEY) => controls.abort(),
+            _ => (),
+        });
+
+        if let Err(error) = result {
+            warn!(?error, "hotkeys unavailable");
+        }
+    });
+}
+
+/// Takes over the terminal and redraws it until the process ends, giving it
+/// back on a panic or Ctrl-C as well
+pub fn spawn(view: Arc<Mutex<View>>, ace_state: Arc<Mutex<AceState>>, controls: Arc<Controls>) {
+    ACTIVE.store(true, Ordering::Relaxed);
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore();
+        default_hook(info);
+    }));
+
+    tokio::spawn(async {
+        if signal::ctrl_c().await.is_ok() {
+            restore();
+            process::exit(130);
+        }
+    });
+
+    // alternate screen, hidden cursor
+    print!("\x1b[?1049h\x1b[?25l");
+
+    tokio::spawn(async move {
+        loop {
+            let keys = held_keys(&*ace_state.lock().await);
+            let screen = render(&*view.lock().await, &keys, &controls);
+
+            draw(&screen);
+
+            sleep(RENDER_INTERVAL).await;
+        }
+    });
+}
+
+fn draw(screen: &str) {
+    let mut stdout = stdout().lock();
+    let _ = write!(stdout, "\x1b[H\x1b[2J{screen}");
+    let _ = stdout.flush();
+}
+
+/// Gives the terminal back, printing the warnings that were only on screen
+pub fn restore() {
+    if !ACTIVE.swap(false, Ordering::Relaxed) {
+        return;
+    }
+
+    print!("\x1b[?25h\x1b[?1049l");
+    let _ = stdout().flush();
+
+    // the panic being restored from may have poisoned it
+    let mut warnings = WARNINGS.lock().unwrap_or_else(PoisonError::into_inner);
+
+    for warning in warnings.drain(..) {
+        eprintln!("{warning}");
+    }
+}
+
+fn held_keys(ace_state: &AceState) -> Vec<&'static str> {
+    [
+        (ace_state.is_walking, "W"),
+        (ace_state.is_running, "Control"),
+        (ace_state.is_sneaking, "Shift"),
+        (ace_state.is_pressing_right_click, "Mouse Right"),
+    ]
+    .into_iter()
+    .filter_map(|(held, key)| held.then_some(key))
+    .collect()
+}
+
+fn render(view: &View, keys: &[&str], controls: &Controls) -> String {
+    let mut screen = String::new();
+
+    let state = match (view.remaining, controls.is_paused()) {
+        (0, _) => "finished",
+        (_, true) => "paused",
+        (_, false) => "running",
+    };
+
+    let done = view.plan_length - view.remaining;
+    let filled = match view.plan_length {
+        0 => PROGRESS_WIDTH,
+        length => done * PROGRESS_WIDTH / length,
+    };
+
+    let _ = writeln!(
+        screen,
+        "acecraft walk [{state}]    {PAUSE_KEY:?} pause/resume  {ABORT_KEY:?} abort"
+    );
+    let _ = writeln!(screen);
+
+    let _ = writeln!(
+        screen,
+        "[{}{}] {done}/{}",
+        "#".repeat(filled),
+        ".".repeat(PROGRESS_WIDTH - filled),
+        view.plan_length
+    );
+
+    let _ = writeln!(
+        screen,
+        "{}",
+        view.instruction.as_deref().unwrap_or("No instruction left")
+    );
+    let _ = writeln!(screen);
+
+    let _ = writeln!(
+        screen,
+        "Distance   {}",
+        view.distance
+            .map_or("-".to_owned(), |distance| format!("{distance:.2}"))
+    );
+    let _ = writeln!(
+        screen,
+        "Yaw error  {}",
+        view.yaw_error
+            .map_or("-".to_owned(), |yaw| format!("{yaw:.1}°"))
+    );
+
+    let keys = match keys.is_empty() {
+        true => "-".to_owned(),
+        false => keys.join(" "),
+    };
+    let _ = writeln!(screen, "Held keys  {keys}");
+
+    let telemetry = match view.telemetry_at.map(|at| at.elapsed()) {
+        None => "waiting for the mod".to_owned(),
+        Some(age) if age > STALE_TELEMETRY => {
+            format!("\x1b[31m{:.1}s ago\x1b[0m", age.as_secs_f64())
+        }
+        Some(age) => format!("{:.1}s ago", age.as_secs_f64()),
+    };
+    let _ = writeln!(screen, "Telemetry  {telemetry}");
+    let _ = writeln!(screen);
+
+    let _ = writeln!(screen, "Warnings");
+
+    for warning in WARNINGS.lock().unwrap().iter() {
+        let _ = writeln!(screen, "  \x1b[33m{warning}\x1b[0m");
+    }
+
+    screen
+}
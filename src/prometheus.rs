@@ -0,0 +1,58 @@
+//! Prometheus text exposition helpers shared by the client and the relay
+
+use std::fmt::Write;
+
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    bounds: Vec<f64>,
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &[f64]) -> Self {
+        Self {
+            bounds: bounds.to_vec(),
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    pub fn observe(&mut self, value: f64) {
+        for (bound, count) in self.bounds.iter().zip(self.counts.iter_mut()) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+pub fn write_samples(out: &mut String, name: &str, kind: &str, help: &str, samples: &[(String, f64)]) {
+    writeln!(out, "# HELP {name} {help}").unwrap();
+    writeln!(out, "# TYPE {name} {kind}").unwrap();
+
+    for (labels, value) in samples {
+        match labels.is_empty() {
+            true => writeln!(out, "{name} {value}").unwrap(),
+            false => writeln!(out, "{name}{{{labels}}} {value}").unwrap(),
+        }
+    }
+}
+
+pub fn write_histogram(out: &mut String, name: &str, help: &str, histogram: &Histogram) {
+    writeln!(out, "# HELP {name} {help}").unwrap();
+    writeln!(out, "# TYPE {name} histogram").unwrap();
+
+    for (bound, count) in histogram.bounds.iter().zip(&histogram.counts) {
+        writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {count}").unwrap();
+    }
+
+    writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {}", histogram.count).unwrap();
+    writeln!(out, "{name}_sum {}", histogram.sum).unwrap();
+    writeln!(out, "{name}_count {}", histogram.count).unwrap();
+}
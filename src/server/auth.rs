@@ -0,0 +1,68 @@
+use actix_web::{http::header, HttpRequest, HttpResponse};
+
+use crate::config::ServerConfig;
+
+#[derive(Debug)]
+pub enum AuthError {
+    MissingToken,
+    InvalidToken { role: String },
+}
+
+impl AuthError {
+    pub fn response(&self) -> HttpResponse {
+        match self {
+            AuthError::MissingToken => HttpResponse::Unauthorized()
+                .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+                .body("missing token"),
+
+            AuthError::InvalidToken { role } => {
+                HttpResponse::Forbidden().body(format!("invalid token for role '{role}'"))
+            }
+        }
+    }
+}
+
+/// Checks the token presented during the websocket handshake or an HTTP API
+/// request, either as an `Authorization: Bearer` header or as a `token` query
+/// parameter for browsers
+pub fn authorize(
+    req: &HttpRequest,
+    query_token: Option<&str>,
+    role: &str,
+    config: &ServerConfig,
+) -> Result<(), AuthError> {
+    if !config.requires_token() {
+        return Ok(());
+    }
+
+    let header_token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    let token = header_token
+        .or(query_token)
+        .ok_or(AuthError::MissingToken)?;
+
+    let accepted = config
+        .token
+        .iter()
+        .chain(config.role_tokens.get(role))
+        .any(|expected| constant_time_eq(expected.as_bytes(), token.as_bytes()));
+
+    match accepted {
+        true => Ok(()),
+        false => Err(AuthError::InvalidToken {
+            role: role.to_owned(),
+        }),
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0, |difference, (x, y)| difference | (x ^ y)) == 0
+}
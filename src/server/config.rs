@@ -0,0 +1,42 @@
+use std::{collections::HashMap, fs, io::ErrorKind};
+
+use serde::Deserialize;
+
+pub const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ServerConfig {
+    /// Shared secret accepted for every role
+    pub token: Option<String>,
+    /// Secrets accepted only for the role they are keyed by
+    #[serde(default)]
+    pub role_tokens: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    server: ServerConfig,
+}
+
+impl ServerConfig {
+    pub fn requires_token(&self) -> bool {
+        self.token.is_some() || !self.role_tokens.is_empty()
+    }
+}
+
+/// Reads the `[server]` table of the config file, a missing file meaning no authentication
+pub fn load(path: &str) -> ServerConfig {
+    match fs::read_to_string(path) {
+        Ok(content) => {
+            let config: ConfigFile = toml::from_str(&content)
+                .unwrap_or_else(|error| panic!("could not parse {path}: {error}"));
+
+            config.server
+        }
+
+        Err(error) if error.kind() == ErrorKind::NotFound => ServerConfig::default(),
+
+        Err(error) => panic!("could not read {path}: {error}"),
+    }
+}
@@ -0,0 +1,282 @@
+"use strict";
+
+const params = new URLSearchParams(location.search);
+const room = params.get("room") || "default";
+const token = params.get("token");
+
+const map = document.getElementById("map");
+const context = map.getContext("2d");
+
+const state = {
+  plan: [],
+  status: null,
+  player: null,
+  trail: [],
+  socket: null,
+};
+
+const TRAIL_LENGTH = 600;
+const ACTION_COLORS = {
+  fly: "#4fa3e0",
+  walk: "#e0c34f",
+  right_clicking: "#57d46b",
+};
+
+document.getElementById("room").textContent = `room ${room}`;
+
+function log(text) {
+  const list = document.getElementById("log");
+  const item = document.createElement("li");
+
+  item.textContent = `${new Date().toLocaleTimeString()} ${text}`;
+  list.prepend(item);
+
+  while (list.children.length > 50) {
+    list.lastChild.remove();
+  }
+}
+
+function setField(section, field, value) {
+  document.querySelector(`#${section} [data-field="${field}"]`).textContent = value;
+}
+
+function format(number, digits = 1) {
+  return number == null ? "-" : number.toFixed(digits);
+}
+
+function renderStatus() {
+  const status = state.status;
+
+  if (!status) {
+    return;
+  }
+
+  const step =
+    status.step_count > 0
+      ? `${status.step_index + 1}/${status.step_count} ${status.step_name || ""}`
+      : "-";
+
+  setField("status", "state", status.state);
+  setField("status", "step", step);
+  setField("status", "action", status.action || "-");
+  setField("status", "distance", format(status.distance_to_goal));
+  setField("status", "eta", status.eta_seconds == null ? "-" : `${format(status.eta_seconds, 0)}s`);
+  setField("status", "inputs", status.inputs.length > 0 ? status.inputs.join(" ") : "-");
+  setField("status", "error", status.error || "-");
+}
+
+function renderPlayer() {
+  const player = state.player;
+
+  if (!player) {
+    return;
+  }
+
+  setField("player", "position", `${format(player.x)} ${format(player.y)} ${format(player.z)}`);
+  setField("player", "yaw", format(player.yaw));
+}
+
+// World bounds on the x/z plane covering the plan and the player
+function bounds() {
+  const points = state.plan.map((step) => [step.destination[0], step.destination[2]]);
+
+  if (state.player) {
+    points.push([state.player.x, state.player.z]);
+  }
+
+  if (points.length === 0) {
+    return null;
+  }
+
+  const xs = points.map((point) => point[0]);
+  const zs = points.map((point) => point[1]);
+  const size = Math.max(Math.max(...xs) - Math.min(...xs), Math.max(...zs) - Math.min(...zs), 16);
+
+  return {
+    x: (Math.max(...xs) + Math.min(...xs)) / 2,
+    z: (Math.max(...zs) + Math.min(...zs)) / 2,
+    scale: (map.width * 0.9) / size,
+  };
+}
+
+function draw() {
+  context.clearRect(0, 0, map.width, map.height);
+
+  const view = bounds();
+
+  if (!view) {
+    return;
+  }
+
+  // North (negative z) is up, east (positive x) is right
+  const project = (x, z) => [
+    map.width / 2 + (x - view.x) * view.scale,
+    map.height / 2 + (z - view.z) * view.scale,
+  ];
+
+  const current = state.status ? state.status.step_index : -1;
+
+  state.plan.forEach((step, index) => {
+    const [x, y] = project(step.destination[0], step.destination[2]);
+
+    if (index > 0) {
+      const previous = state.plan[index - 1].destination;
+      const [px, py] = project(previous[0], previous[2]);
+
+      context.strokeStyle = ACTION_COLORS[step.action] || "#888";
+      context.globalAlpha = index <= current ? 1 : 0.4;
+      context.beginPath();
+      context.moveTo(px, py);
+      context.lineTo(x, y);
+      context.stroke();
+    }
+
+    context.globalAlpha = 1;
+    context.fillStyle = index === current ? "#ffffff" : ACTION_COLORS[step.action] || "#888";
+    context.beginPath();
+    context.arc(x, y, index === current ? 5 : 3, 0, Math.PI * 2);
+    context.fill();
+  });
+
+  if (state.trail.length > 1) {
+    context.strokeStyle = "#e05d5d";
+    context.globalAlpha = 0.5;
+    context.beginPath();
+    state.trail.forEach(([tx, tz], index) => {
+      const [x, y] = project(tx, tz);
+      index === 0 ? context.moveTo(x, y) : context.lineTo(x, y);
+    });
+    context.stroke();
+    context.globalAlpha = 1;
+  }
+
+  if (state.player) {
+    const [x, y] = project(state.player.x, state.player.z);
+    const yaw = (state.player.yaw * Math.PI) / 180;
+
+    // Minecraft yaw 0 faces positive z and grows clockwise seen from above
+    const dx = -Math.sin(yaw);
+    const dz = Math.cos(yaw);
+
+    context.fillStyle = "#e05d5d";
+    context.beginPath();
+    context.arc(x, y, 6, 0, Math.PI * 2);
+    context.fill();
+
+    context.strokeStyle = "#e05d5d";
+    context.lineWidth = 2;
+    context.beginPath();
+    context.moveTo(x, y);
+    context.lineTo(x + dx * 20, y + dz * 20);
+    context.stroke();
+    context.lineWidth = 1;
+  }
+}
+
+function handleFrame(frame) {
+  switch (frame.type) {
+    case "plan":
+      state.plan = frame.steps;
+      log(`plan with ${frame.steps.length} steps`);
+      break;
+
+    case "status":
+      state.status = frame;
+      renderStatus();
+      break;
+
+    case "ack":
+      log(`${frame.command || "command"} ${frame.ok ? "ok" : `failed: ${frame.error}`}`);
+      break;
+
+    case undefined:
+      if (frame.coords) {
+        state.player = {
+          x: frame.coords.x,
+          y: frame.coords.y,
+          z: frame.coords.z,
+          yaw: frame.head ? frame.head.yaw : 0,
+        };
+
+        state.trail.push([frame.coords.x, frame.coords.z]);
+        state.trail.splice(0, state.trail.length - TRAIL_LENGTH);
+        renderPlayer();
+      }
+      break;
+  }
+
+  draw();
+}
+
+// Picks up what the relay remembers so a late dashboard is not blank
+async function loadRoomState() {
+  const query = token ? `?${new URLSearchParams({ token })}` : "";
+  const response = await fetch(`/rooms/${encodeURIComponent(room)}/state${query}`);
+
+  if (!response.ok) {
+    return;
+  }
+
+  const roomState = await response.json();
+
+  for (const frame of [roomState.plan, roomState.status, roomState.telemetry]) {
+    if (frame) {
+      handleFrame(frame);
+    }
+  }
+}
+
+function connect() {
+  const query = new URLSearchParams({ room, role: "observer" });
+
+  if (token) {
+    query.set("token", token);
+  }
+
+  const scheme = location.protocol === "https:" ? "wss" : "ws";
+  const socket = new WebSocket(`${scheme}://${location.host}/ws?${query}`);
+  const connection = document.getElementById("connection");
+
+  socket.onopen = () => {
+    connection.textContent = "online";
+    connection.className = "online";
+    log("connected");
+  };
+
+  socket.onclose = () => {
+    connection.textContent = "offline";
+    connection.className = "offline";
+    state.socket = null;
+    log("disconnected, retrying in 2s");
+    setTimeout(connect, 2000);
+  };
+
+  socket.onmessage = (event) => {
+    try {
+      handleFrame(JSON.parse(event.data));
+    } catch (error) {
+      console.warn("ignored frame", event.data, error);
+    }
+  };
+
+  state.socket = socket;
+}
+
+let nextCommandId = 1;
+
+function sendCommand(command) {
+  if (!state.socket || state.socket.readyState !== WebSocket.OPEN) {
+    log(`cannot ${command}, not connected`);
+    return;
+  }
+
+  const id = `dashboard-${nextCommandId++}`;
+  state.socket.send(JSON.stringify({ type: "command", id, command }));
+  log(`sent ${command}`);
+}
+
+for (const button of document.querySelectorAll("[data-command]")) {
+  button.addEventListener("click", () => sendCommand(button.dataset.command));
+}
+
+loadRoomState().finally(connect);
@@ -0,0 +1,117 @@
+use actix::Addr;
+use actix_web::{error::ErrorInternalServerError, web, Error, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::{
+    auth::{self, AuthError},
+    config::ServerConfig,
+    websocket_server::{GetRoomState, KickSession, ListSessions, RenderMetrics, Server},
+};
+
+/// Role whose token reads the state of a room, as the dashboard does
+const ROOM_STATE_ROLE: &str = "observer";
+/// Role whose token lists and kicks sessions and reads the metrics
+const ADMIN_ROLE: &str = "admin";
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/sessions", web::get().to(list_sessions))
+        .route("/sessions/{id}/kick", web::post().to(kick_session))
+        .route("/rooms/{id}/state", web::get().to(room_state))
+        .route("/metrics", web::get().to(metrics));
+}
+
+/// The token check of the websocket handshake, for the role the endpoint needs
+fn guard(req: &HttpRequest, config: &ServerConfig, role: &str) -> Result<(), AuthError> {
+    let query = web::Query::<TokenQuery>::from_query(req.query_string()).ok();
+    let query_token = query.as_ref().and_then(|query| query.token.as_deref());
+
+    auth::authorize(req, query_token, role, config).inspect_err(|error| {
+        warn!(?error, %role, path = %req.path(), "rejected API request");
+    })
+}
+
+async fn list_sessions(
+    req: HttpRequest,
+    config: web::Data<ServerConfig>,
+    srv: web::Data<Addr<Server>>,
+) -> Result<HttpResponse, Error> {
+    if let Err(error) = guard(&req, &config, ADMIN_ROLE) {
+        return Ok(error.response());
+    }
+
+    let sessions = srv
+        .send(ListSessions)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(sessions))
+}
+
+async fn room_state(
+    req: HttpRequest,
+    room: web::Path<String>,
+    config: web::Data<ServerConfig>,
+    srv: web::Data<Addr<Server>>,
+) -> Result<HttpResponse, Error> {
+    if let Err(error) = guard(&req, &config, ROOM_STATE_ROLE) {
+        return Ok(error.response());
+    }
+
+    let room_info = srv
+        .send(GetRoomState {
+            room: room.into_inner(),
+        })
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    Ok(match room_info {
+        Some(room_info) => HttpResponse::Ok().json(room_info),
+        None => HttpResponse::NotFound().finish(),
+    })
+}
+
+async fn kick_session(
+    req: HttpRequest,
+    id: web::Path<usize>,
+    config: web::Data<ServerConfig>,
+    srv: web::Data<Addr<Server>>,
+) -> Result<HttpResponse, Error> {
+    if let Err(error) = guard(&req, &config, ADMIN_ROLE) {
+        return Ok(error.response());
+    }
+
+    let kicked = srv
+        .send(KickSession { id: id.into_inner() })
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    Ok(match kicked {
+        true => HttpResponse::NoContent().finish(),
+        false => HttpResponse::NotFound().finish(),
+    })
+}
+
+async fn metrics(
+    req: HttpRequest,
+    config: web::Data<ServerConfig>,
+    srv: web::Data<Addr<Server>>,
+) -> Result<HttpResponse, Error> {
+    if let Err(error) = guard(&req, &config, ADMIN_ROLE) {
+        return Ok(error.response());
+    }
+
+    let metrics = srv
+        .send(RenderMetrics)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics))
+}
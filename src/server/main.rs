@@ -1,21 +1,72 @@
-use std::time::Instant;
+use std::{env, process, time::Instant};
 
 use actix::*;
 use actix_web::{web, App, Error, HttpRequest, HttpResponse, HttpServer};
 
 use actix_web_actors::ws;
+use config::ServerConfig;
+use recording::Recorder;
+use replay::{Pace, ReplayOptions};
+use serde::Deserialize;
+use tracing::{error, info, warn};
+
+mod auth;
+mod config;
+mod dashboard;
+mod http_api;
+mod logging;
+mod metrics;
+#[path = "../prometheus.rs"]
+mod prometheus;
+mod recording;
+mod replay;
 mod websocket_server;
 mod websocket_session;
 
+const DEFAULT_ROOM: &str = "default";
+const DEFAULT_ROLE: &str = "client";
+const USAGE: &str = "usage:
+  server [--config <config.toml>] [--record <file.jsonl>] [--log-json]
+  server replay <file.jsonl> [--room <room>] [--speed <multiplier> | --step] [--config <config.toml>] [--record <file.jsonl>] [--log-json]
+
+RUST_LOG sets the log levels, e.g. RUST_LOG=info,server::websocket_server=debug";
+
+#[derive(Deserialize)]
+struct WebSocketQuery {
+    room: Option<String>,
+    role: Option<String>,
+    token: Option<String>,
+}
+
+#[derive(Default)]
+struct Options {
+    config: Option<String>,
+    record: Option<String>,
+    replay: Option<ReplayOptions>,
+    log_json: bool,
+}
+
 async fn ws_route(
     req: HttpRequest,
     stream: web::Payload,
+    query: web::Query<WebSocketQuery>,
+    config: web::Data<ServerConfig>,
     srv: web::Data<Addr<websocket_server::Server>>,
 ) -> Result<HttpResponse, Error> {
+    let query = query.into_inner();
+    let role = query.role.unwrap_or_else(|| DEFAULT_ROLE.to_owned());
+
+    if let Err(error) = auth::authorize(&req, query.token.as_deref(), &role, &config) {
+        warn!(?error, %role, "rejected websocket handshake");
+        return Ok(error.response());
+    }
+
     ws::start(
         websocket_session::WebSocketSession {
             id: 0,
             heartbeat: Instant::now(),
+            room: query.room.unwrap_or_else(|| DEFAULT_ROOM.to_owned()),
+            role,
             addr: srv.get_ref().clone(),
         },
         &req,
@@ -23,16 +74,101 @@ async fn ws_route(
     )
 }
 
+fn exit_with_usage(reason: &str) -> ! {
+    eprintln!("{reason}\n{USAGE}");
+    process::exit(2);
+}
+
+fn parse_options() -> Options {
+    let mut options = Options::default();
+    let mut args = env::args().skip(1).peekable();
+
+    if args.peek().map(String::as_str) == Some("replay") {
+        args.next();
+
+        let path = args
+            .next()
+            .unwrap_or_else(|| exit_with_usage("missing recording to replay"));
+
+        options.replay = Some(ReplayOptions {
+            path,
+            room: None,
+            pace: Pace::Speed(1.0),
+        });
+    }
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| exit_with_usage(&format!("missing value for {arg}")))
+        };
+
+        match (arg.as_str(), options.replay.as_mut()) {
+            ("--config", _) => options.config = Some(value()),
+            ("--record", _) => options.record = Some(value()),
+            ("--log-json", _) => options.log_json = true,
+            ("--room", Some(replay)) => replay.room = Some(value()),
+            ("--speed", Some(replay)) => {
+                let speed = value()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|speed| *speed > 0.0)
+                    .unwrap_or_else(|| exit_with_usage("--speed must be a positive number"));
+
+                replay.pace = Pace::Speed(speed);
+            }
+            ("--step", Some(replay)) => replay.pace = Pace::Step,
+            _ => exit_with_usage(&format!("unexpected argument {arg}")),
+        }
+    }
+
+    options
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let server = websocket_server::Server::new().start();
+    let options = parse_options();
+    logging::init(options.log_json);
+
+    let config_path = options
+        .config
+        .as_deref()
+        .unwrap_or(config::DEFAULT_CONFIG_PATH);
+    let config = web::Data::new(config::load(config_path));
+
+    if !config.requires_token() {
+        warn!("no token configured in {config_path}, /ws and the HTTP API accept anyone");
+    }
+
+    let recorder = match &options.record {
+        Some(path) => {
+            info!("recording relayed frames to {path}");
+            Some(Recorder::create(path)?)
+        }
+        None => None,
+    };
+
+    let server = websocket_server::Server::new(recorder).start();
+
+    if let Some(replay) = options.replay {
+        let frames = recording::read(&replay.path).map_err(|error| {
+            error!(path = %replay.path, %error, "cannot read the recording to replay");
+            error
+        })?;
+
+        tokio::spawn(replay::run(replay, frames, server.clone()));
+    }
 
-    println!("starting HTTP server at http://localhost:8080");
+    info!("starting HTTP server at http://localhost:8080");
+    info!("dashboard at http://localhost:8080/?room={DEFAULT_ROOM}");
 
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(server.clone()))
+            .app_data(config.clone())
             .route("/ws", web::get().to(ws_route))
+            .configure(http_api::configure)
+            .configure(dashboard::configure)
     })
     .workers(2)
     .bind("127.0.0.1:8080")
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use crate::prometheus::{write_histogram, write_samples, Histogram};
+
+/// Upper bounds, in seconds, for the relay fan-out latency histogram
+const FANOUT_BUCKETS: [f64; 8] = [0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5];
+
+/// Counters the relay keeps for the `/metrics` endpoint
+#[derive(Debug)]
+pub struct RelayMetrics {
+    /// Text frames received, keyed by (room, role)
+    pub frames: HashMap<(String, String), u64>,
+    /// Time from a frame reaching the relay to a session of its room writing
+    /// it out, once per session
+    pub fanout_seconds: Histogram,
+    pub heartbeat_timeouts: u64,
+    pub kicks: u64,
+}
+
+impl RelayMetrics {
+    pub fn new() -> Self {
+        Self {
+            frames: HashMap::new(),
+            fanout_seconds: Histogram::new(&FANOUT_BUCKETS),
+            heartbeat_timeouts: 0,
+            kicks: 0,
+        }
+    }
+
+    pub fn count_frame(&mut self, room: &str, role: &str) {
+        *self
+            .frames
+            .entry((room.to_owned(), role.to_owned()))
+            .or_insert(0) += 1;
+    }
+
+    /// Renders every metric in the Prometheus text exposition format
+    pub fn render(&self, sessions: &HashMap<(String, String), usize>) -> String {
+        let mut out = String::new();
+
+        let frames: Vec<(String, f64)> = self
+            .frames
+            .iter()
+            .map(|((room, role), value)| (labels(&[("room", room), ("role", role)]), *value as f64))
+            .collect();
+
+        write_samples(
+            &mut out,
+            "acecraft_relay_frames_total",
+            "counter",
+            "Text frames received by the relay",
+            &frames,
+        );
+
+        let sessions: Vec<(String, f64)> = sessions
+            .iter()
+            .map(|((room, role), value)| (labels(&[("room", room), ("role", role)]), *value as f64))
+            .collect();
+
+        write_samples(
+            &mut out,
+            "acecraft_relay_sessions",
+            "gauge",
+            "Websocket sessions currently connected",
+            &sessions,
+        );
+
+        write_histogram(
+            &mut out,
+            "acecraft_relay_fanout_seconds",
+            "Time from a frame reaching the relay to each session writing it out",
+            &self.fanout_seconds,
+        );
+
+        write_samples(
+            &mut out,
+            "acecraft_relay_heartbeat_timeouts_total",
+            "counter",
+            "Sessions dropped because they stopped answering pings",
+            &[(String::new(), self.heartbeat_timeouts as f64)],
+        );
+
+        write_samples(
+            &mut out,
+            "acecraft_relay_kicks_total",
+            "counter",
+            "Sessions closed through the HTTP API",
+            &[(String::new(), self.kicks as f64)],
+        );
+
+        out
+    }
+}
+
+pub fn labels(pairs: &[(&str, &str)]) -> String {
+    pairs
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+
+            format!("{name}=\"{value}\"")
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
@@ -0,0 +1,70 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, LineWriter, Write},
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// One line of a recording: a text frame relayed into a room, stamped with the
+/// milliseconds elapsed since the recording started
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RecordedFrame {
+    pub at_ms: u64,
+    pub room: String,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub struct Recorder {
+    file: LineWriter<File>,
+    started: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &str) -> io::Result<Self> {
+        Ok(Self {
+            file: LineWriter::new(File::create(path)?),
+            started: Instant::now(),
+        })
+    }
+
+    pub fn write(&mut self, room: &str, message: &str) {
+        let frame = RecordedFrame {
+            at_ms: self.started.elapsed().as_millis() as u64,
+            room: room.to_owned(),
+            message: message.to_owned(),
+        };
+
+        let line = serde_json::to_string(&frame).unwrap();
+
+        if let Err(error) = writeln!(self.file, "{line}") {
+            warn!(%error, "could not record frame");
+        }
+    }
+}
+
+pub fn read(path: &str) -> io::Result<Vec<RecordedFrame>> {
+    let file = BufReader::new(File::open(path)?);
+    let mut frames = Vec::new();
+
+    for (index, line) in file.lines().enumerate() {
+        let line = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let frame = serde_json::from_str(&line).map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{path}:{}: {error}", index + 1),
+            )
+        })?;
+
+        frames.push(frame);
+    }
+
+    Ok(frames)
+}
@@ -0,0 +1,109 @@
+use std::time::{Duration, Instant};
+
+use actix::Addr;
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    time::sleep,
+};
+use tracing::{info, warn};
+
+use crate::{
+    recording::RecordedFrame,
+    websocket_server::{ClientMessage, CountSessions, Server},
+};
+
+/// Session id used for frames injected by the replay, so no connected session is skipped
+const REPLAY_SESSION_ID: usize = 0;
+const WAIT_FOR_CONSUMER_INTERVAL: Duration = Duration::from_millis(500);
+
+pub enum Pace {
+    /// Keeps the recorded gaps between frames, divided by the multiplier
+    Speed(f64),
+    /// Sends one frame every time Enter is pressed on stdin
+    Step,
+}
+
+pub struct ReplayOptions {
+    pub path: String,
+    pub room: Option<String>,
+    pub pace: Pace,
+}
+
+/// Replays frames already read from the recording at `options.path`
+pub async fn run(options: ReplayOptions, frames: Vec<RecordedFrame>, server: Addr<Server>) {
+    let Some(first_frame) = frames.first() else {
+        warn!(path = %options.path, "nothing to replay");
+        return;
+    };
+
+    let first_room = room_of(&options, first_frame);
+    info!(
+        frames = frames.len(),
+        path = %options.path,
+        room = %first_room,
+        "replay waits for a client to join"
+    );
+
+    while server
+        .send(CountSessions {
+            room: first_room.clone(),
+        })
+        .await
+        .unwrap_or(0)
+        == 0
+    {
+        sleep(WAIT_FOR_CONSUMER_INTERVAL).await;
+    }
+
+    match options.pace {
+        Pace::Speed(speed) => {
+            let started = Instant::now();
+            let offset = first_frame.at_ms;
+
+            for frame in &frames {
+                let due = Duration::from_millis(frame.at_ms.saturating_sub(offset)).div_f64(speed);
+
+                if let Some(wait) = due.checked_sub(started.elapsed()) {
+                    sleep(wait).await;
+                }
+
+                send(&server, &options, frame);
+            }
+        }
+
+        Pace::Step => {
+            let mut lines = BufReader::new(tokio::io::stdin()).lines();
+
+            for (index, frame) in frames.iter().enumerate() {
+                println!(
+                    "[{}/{}] +{}ms, Enter to send, q to stop: {}",
+                    index + 1,
+                    frames.len(),
+                    frame.at_ms,
+                    frame.message
+                );
+
+                match lines.next_line().await {
+                    Ok(Some(line)) if line.trim() == "q" => break,
+                    Ok(Some(_)) => send(&server, &options, frame),
+                    _ => break,
+                }
+            }
+        }
+    }
+
+    info!("replay finished");
+}
+
+fn room_of(options: &ReplayOptions, frame: &RecordedFrame) -> String {
+    options.room.clone().unwrap_or_else(|| frame.room.clone())
+}
+
+fn send(server: &Addr<Server>, options: &ReplayOptions, frame: &RecordedFrame) {
+    server.do_send(ClientMessage {
+        id: REPLAY_SESSION_ID,
+        room: room_of(options, frame),
+        message: frame.message.clone(),
+        received_at: Instant::now(),
+    });
+}
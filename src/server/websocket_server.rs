@@ -1,16 +1,42 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use actix::prelude::*;
-use rand::{self, rngs::ThreadRng, Rng};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::{info, warn};
 
+use crate::{metrics::RelayMetrics, recording::Recorder};
+
+/// A frame for a session to write out, with the time the relay received it
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Message {
+    pub text: String,
+    pub received_at: Instant,
+}
+
+/// Sent by a session once it wrote a frame out, for the fan-out latency
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Delivered {
+    pub latency: Duration,
+}
+
+/// Asks a session to close its websocket
 #[derive(Message)]
 #[rtype(result = "()")]
-pub struct Message(pub String);
+pub struct Kick;
 
 #[derive(Message)]
 #[rtype(usize)]
 pub struct Connect {
     pub addr: Recipient<Message>,
+    pub kick: Recipient<Kick>,
+    pub room: String,
+    pub role: String,
 }
 
 #[derive(Message)]
@@ -19,24 +45,118 @@ pub struct Disconnect {
     pub id: usize,
 }
 
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Heartbeat {
+    pub id: usize,
+}
+
+/// Sent by a session right before it drops a client that stopped answering pings
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct HeartbeatTimeout {
+    pub id: usize,
+}
+
 #[derive(Message)]
 #[rtype(result = "()")]
 pub struct ClientMessage {
   pub id: usize,
+  pub room: String,
   pub message: String,
+  pub received_at: Instant,
+}
+
+/// Number of sessions currently joined to a room
+#[derive(Message)]
+#[rtype(usize)]
+pub struct CountSessions {
+    pub room: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "Vec<SessionInfo>")]
+pub struct ListSessions;
+
+#[derive(Message)]
+#[rtype(result = "Option<RoomInfo>")]
+pub struct GetRoomState {
+    pub room: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "String")]
+pub struct RenderMetrics;
+
+/// Closes a session, answering whether it existed
+#[derive(Message)]
+#[rtype(bool)]
+pub struct KickSession {
+    pub id: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionInfo {
+    pub id: usize,
+    pub role: String,
+    pub room: String,
+    pub connected_at_ms: u64,
+    pub last_heartbeat_ms: u64,
+    pub messages_received: u64,
+    pub messages_sent: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoomInfo {
+    pub room: String,
+    pub sessions: usize,
+    pub telemetry: Option<Value>,
+    pub telemetry_at_ms: Option<u64>,
+    pub status: Option<Value>,
+    pub status_at_ms: Option<u64>,
+    pub plan: Option<Value>,
+}
+
+#[derive(Debug)]
+struct Session {
+    addr: Recipient<Message>,
+    kick: Recipient<Kick>,
+    room: String,
+    role: String,
+    connected_at: SystemTime,
+    last_heartbeat: SystemTime,
+    messages_received: u64,
+    messages_sent: u64,
+}
+
+/// What the relay remembers about a room after its frames went through
+#[derive(Debug, Default)]
+struct Room {
+    telemetry: Option<Value>,
+    telemetry_at: Option<SystemTime>,
+    status: Option<Value>,
+    status_at: Option<SystemTime>,
+    plan: Option<Value>,
 }
 
 #[derive(Debug)]
 pub struct Server {
-    sessions: HashMap<usize, Recipient<Message>>,
-    rng: ThreadRng,
+    sessions: HashMap<usize, Session>,
+    rooms: HashMap<String, Room>,
+    recorder: Option<Recorder>,
+    metrics: RelayMetrics,
+    /// Id of the next session, starting after the one the replay uses
+    next_id: usize,
 }
 
 impl Server {
-    pub fn new() -> Self {
+    pub fn new(recorder: Option<Recorder>) -> Self {
         Server {
             sessions: HashMap::new(),
-            rng: rand::thread_rng(),
+            rooms: HashMap::new(),
+            recorder,
+            metrics: RelayMetrics::new(),
+            next_id: 1,
         }
     }
 }
@@ -45,14 +165,48 @@ impl Actor for Server {
     type Context = Context<Self>;
 }
 
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
 impl Server {
-    fn broadcast_message(&mut self, message: &str, skip_id: usize) {
-        for (id, _recipient) in &self.sessions {
-          if *id != skip_id {
-            if let Some(addr) = self.sessions.get(&id) {
-              addr.do_send(Message(message.to_owned()));
+    fn broadcast_message(
+        &mut self,
+        room: &str,
+        message: &str,
+        received_at: Instant,
+        skip_id: usize,
+    ) {
+        for (id, session) in &mut self.sessions {
+            if *id != skip_id && session.room == room {
+                session.addr.do_send(Message {
+                    text: message.to_owned(),
+                    received_at,
+                });
+                session.messages_sent += 1;
             }
-          }
+        }
+    }
+
+    /// Keeps the latest telemetry frame, the only untyped JSON carrying `coords`,
+    /// and the latest status and plan frames published by the bot
+    fn remember(&mut self, room: &str, message: &str) {
+        let Ok(value) = serde_json::from_str::<Value>(message) else {
+            return;
+        };
+
+        if value["type"] == "status" {
+            let room = self.rooms.entry(room.to_owned()).or_default();
+            room.status = Some(value);
+            room.status_at = Some(SystemTime::now());
+        } else if value["type"] == "plan" {
+            self.rooms.entry(room.to_owned()).or_default().plan = Some(value);
+        } else if value.get("coords").is_some() {
+            let room = self.rooms.entry(room.to_owned()).or_default();
+            room.telemetry = Some(value);
+            room.telemetry_at = Some(SystemTime::now());
         }
     }
 }
@@ -61,8 +215,31 @@ impl Handler<Connect> for Server {
     type Result = usize;
 
     fn handle(&mut self, message_received: Connect, _ctx: &mut Context<Self>) -> Self::Result {
-        let id = self.rng.gen::<usize>();
-        self.sessions.insert(id, message_received.addr);
+        let id = self.next_id;
+        self.next_id += 1;
+        let now = SystemTime::now();
+
+        info!(
+            session = id,
+            room = %message_received.room,
+            role = %message_received.role,
+            "session joined"
+        );
+
+        self.rooms.entry(message_received.room.clone()).or_default();
+        self.sessions.insert(
+            id,
+            Session {
+                addr: message_received.addr,
+                kick: message_received.kick,
+                room: message_received.room,
+                role: message_received.role,
+                connected_at: now,
+                last_heartbeat: now,
+                messages_received: 0,
+                messages_sent: 0,
+            },
+        );
         id
     }
 }
@@ -71,14 +248,166 @@ impl Handler<Disconnect> for Server {
     type Result = ();
 
     fn handle(&mut self, message_received: Disconnect, _ctx: &mut Context<Self>) -> Self::Result {
-        self.sessions.remove(&message_received.id);
+        let Some(session) = self.sessions.remove(&message_received.id) else {
+            return;
+        };
+
+        let is_empty = !self
+            .sessions
+            .values()
+            .any(|other| other.room == session.room);
+
+        if is_empty {
+            self.rooms.remove(&session.room);
+        }
+    }
+}
+
+impl Handler<Heartbeat> for Server {
+    type Result = ();
+
+    fn handle(&mut self, message_received: Heartbeat, _ctx: &mut Context<Self>) -> Self::Result {
+        if let Some(session) = self.sessions.get_mut(&message_received.id) {
+            session.last_heartbeat = SystemTime::now();
+        }
+    }
+}
+
+impl Handler<HeartbeatTimeout> for Server {
+    type Result = ();
+
+    fn handle(&mut self, message_received: HeartbeatTimeout, _ctx: &mut Context<Self>) -> Self::Result {
+        warn!(session = message_received.id, "session timed out");
+        self.metrics.heartbeat_timeouts += 1;
     }
 }
 
 impl Handler<ClientMessage> for Server {
   type Result = ();
-  
+
   fn handle(&mut self, message_received: ClientMessage, _ctx: &mut Context<Self>) -> () {
-    self.broadcast_message(message_received.message.as_str(), message_received.id);
+    let role = match self.sessions.get_mut(&message_received.id) {
+      Some(session) => {
+        session.messages_received += 1;
+        session.role.clone()
+      }
+
+      None => "replay".to_owned(),
+    };
+
+    self.metrics.count_frame(&message_received.room, &role);
+
+    if let Some(recorder) = &mut self.recorder {
+      recorder.write(&message_received.room, &message_received.message);
+    }
+
+    self.remember(&message_received.room, &message_received.message);
+
+    self.broadcast_message(
+      &message_received.room,
+      &message_received.message,
+      message_received.received_at,
+      message_received.id,
+    );
   }
-}
\ No newline at end of file
+}
+
+impl Handler<Delivered> for Server {
+    type Result = ();
+
+    fn handle(&mut self, message_received: Delivered, _ctx: &mut Context<Self>) -> Self::Result {
+        self.metrics
+            .fanout_seconds
+            .observe(message_received.latency.as_secs_f64());
+    }
+}
+
+impl Handler<CountSessions> for Server {
+    type Result = usize;
+
+    fn handle(&mut self, message_received: CountSessions, _ctx: &mut Context<Self>) -> Self::Result {
+        self.sessions
+            .values()
+            .filter(|session| session.room == message_received.room)
+            .count()
+    }
+}
+
+impl Handler<ListSessions> for Server {
+    type Result = MessageResult<ListSessions>;
+
+    fn handle(&mut self, _message_received: ListSessions, _ctx: &mut Context<Self>) -> Self::Result {
+        let mut sessions: Vec<SessionInfo> = self
+            .sessions
+            .iter()
+            .map(|(id, session)| SessionInfo {
+                id: *id,
+                role: session.role.clone(),
+                room: session.room.clone(),
+                connected_at_ms: unix_ms(session.connected_at),
+                last_heartbeat_ms: unix_ms(session.last_heartbeat),
+                messages_received: session.messages_received,
+                messages_sent: session.messages_sent,
+            })
+            .collect();
+
+        sessions.sort_by_key(|session| session.connected_at_ms);
+
+        MessageResult(sessions)
+    }
+}
+
+impl Handler<GetRoomState> for Server {
+    type Result = MessageResult<GetRoomState>;
+
+    fn handle(&mut self, message_received: GetRoomState, _ctx: &mut Context<Self>) -> Self::Result {
+        let room_info = self.rooms.get(&message_received.room).map(|room| RoomInfo {
+            room: message_received.room.clone(),
+            sessions: self
+                .sessions
+                .values()
+                .filter(|session| session.room == message_received.room)
+                .count(),
+            telemetry: room.telemetry.clone(),
+            telemetry_at_ms: room.telemetry_at.map(unix_ms),
+            status: room.status.clone(),
+            status_at_ms: room.status_at.map(unix_ms),
+            plan: room.plan.clone(),
+        });
+
+        MessageResult(room_info)
+    }
+}
+
+impl Handler<KickSession> for Server {
+    type Result = bool;
+
+    fn handle(&mut self, message_received: KickSession, _ctx: &mut Context<Self>) -> Self::Result {
+        match self.sessions.get(&message_received.id) {
+            Some(session) => {
+                info!(session = message_received.id, "kicking session");
+                session.kick.do_send(Kick);
+                self.metrics.kicks += 1;
+                true
+            }
+
+            None => false,
+        }
+    }
+}
+
+impl Handler<RenderMetrics> for Server {
+    type Result = String;
+
+    fn handle(&mut self, _message_received: RenderMetrics, _ctx: &mut Context<Self>) -> Self::Result {
+        let mut sessions = HashMap::new();
+
+        for session in self.sessions.values() {
+            *sessions
+                .entry((session.room.clone(), session.role.clone()))
+                .or_insert(0) += 1;
+        }
+
+        self.metrics.render(&sessions)
+    }
+}
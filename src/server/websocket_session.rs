@@ -11,6 +11,8 @@ const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
 pub struct WebSocketSession {
     pub heartbeat: Instant,
     pub id: usize,
+    pub room: String,
+    pub role: String,
     pub addr: Addr<Server>,
 }
 
@@ -18,6 +20,8 @@ impl WebSocketSession {
     fn start_heartbeating(&self, ctx: &mut <Self as Actor>::Context) {
         ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
             if Instant::now().duration_since(act.heartbeat) > CLIENT_TIMEOUT {
+                act.addr
+                    .do_send(websocket_server::HeartbeatTimeout { id: act.id });
                 ctx.stop();
                 return;
             }
@@ -37,7 +41,10 @@ impl Actor for WebSocketSession {
 
         self.addr
             .send(websocket_server::Connect {
-                addr: addr.recipient(),
+                addr: addr.clone().recipient(),
+                kick: addr.recipient(),
+                room: self.room.clone(),
+                role: self.role.clone(),
             })
             .into_actor(self)
             .then(|res, act, ctx| {
@@ -64,7 +71,22 @@ impl Handler<websocket_server::Message> for WebSocketSession {
     type Result = ();
 
     fn handle(&mut self, message_received: websocket_server::Message, ctx: &mut Self::Context) {
-        ctx.text(message_received.0)
+        ctx.text(message_received.text);
+        self.addr.do_send(websocket_server::Delivered {
+            latency: message_received.received_at.elapsed(),
+        });
+    }
+}
+
+impl Handler<websocket_server::Kick> for WebSocketSession {
+    type Result = ();
+
+    fn handle(&mut self, _message_received: websocket_server::Kick, ctx: &mut Self::Context) {
+        ctx.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Policy,
+            description: Some("kicked".to_owned()),
+        }));
+        ctx.stop();
     }
 }
 
@@ -86,11 +108,13 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebSocketSession
         match message {
             ws::Message::Ping(ping_message) => {
                 self.heartbeat = Instant::now();
+                self.addr.do_send(websocket_server::Heartbeat { id: self.id });
                 ctx.pong(&ping_message);
             }
 
             ws::Message::Pong(_) => {
                 self.heartbeat = Instant::now();
+                self.addr.do_send(websocket_server::Heartbeat { id: self.id });
             }
 
             ws::Message::Close(reason) => {
@@ -100,7 +124,9 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebSocketSession
 
             ws::Message::Text(text) => self.addr.do_send(websocket_server::ClientMessage {
                 id: self.id,
+                room: self.room.clone(),
                 message: text.to_owned().to_string(),
+                received_at: Instant::now(),
             }),
 
             _ => (),
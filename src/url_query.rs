@@ -0,0 +1,15 @@
+//! Query strings for the relay handshake, shared by the clients
+
+/// Percent-encodes a query parameter value, leaving only the characters
+/// RFC 3986 calls unreserved as they are
+pub fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}